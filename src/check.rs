@@ -0,0 +1,310 @@
+use std::fmt;
+
+use template::signature;
+use structures::{Root, Package, Module, Class, Function, Attribute, AttributeOrigin, Variable, dotted_path};
+
+/// A structural difference between the config and the python source.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Difference {
+    /// In the config, but not in the source.
+    Missing {kind: &'static str, path: String},
+
+    /// In the source, but not in the config.
+    Extra {kind: &'static str, path: String},
+
+    /// In both, but `what` differs.
+    Changed {what: &'static str, path: String, config: String, source: String},
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Difference::Missing {kind, ref path} => write!(f, "missing {} {}", kind, path),
+            Difference::Extra {kind, ref path} => write!(f, "extra {} {}", kind, path),
+            Difference::Changed {what, ref path, ref config, ref source} => {
+                write!(f, "changed {} of {}\n  config: {}\n  source: {}", what, path, config, source)
+            },
+        }
+    }
+}
+
+fn description(description: &Option<String>) -> String {
+    match *description {
+        Some(ref description) => format!("{:?}", description),
+        None => "none".to_string()
+    }
+}
+
+/// Match the items of the config and the source by name.
+/// Items only on one side are reported as missing or extra, the pairs
+/// are passed on to `compare`.
+fn diff_named<T, N, C>(config: &[T], source: &[T], kind: &'static str, path: &str, name: N, mut compare: C,
+                      differences: &mut Vec<Difference>)
+    where N: Fn(&T) -> &str, C: FnMut(&T, &T, &mut Vec<Difference>)
+{
+    for config_item in config {
+        match source.iter().find(|x| name(x) == name(config_item)) {
+            Some(source_item) => compare(config_item, source_item, differences),
+            None => differences.push(Difference::Missing {kind: kind, path: dotted_path(path, name(config_item))})
+        }
+    }
+
+    for source_item in source {
+        if !config.iter().any(|x| name(x) == name(source_item)) {
+            differences.push(Difference::Extra {kind: kind, path: dotted_path(path, name(source_item))});
+        }
+    }
+}
+
+/// The signature of a function, prefixed with `async` for coroutines.
+fn function_signature(function: &Function) -> String {
+    if function.is_async {
+        format!("async {}", signature(function))
+    } else {
+        signature(function)
+    }
+}
+
+fn decorators(decorators: &[String]) -> String {
+    decorators.iter().map(|x| format!("@{}", x)).collect::<Vec<_>>().join(" ")
+}
+
+fn diff_decorators(config: &[String], source: &[String], path: &str, differences: &mut Vec<Difference>) {
+    if config != source {
+        differences.push(Difference::Changed {
+            what: "decorators",
+            path: path.to_string(),
+            config: decorators(config),
+            source: decorators(source)
+        });
+    }
+}
+
+fn diff_function(config: &Function, source: &Function, path: &str, differences: &mut Vec<Difference>) {
+    let path = dotted_path(path, &config.name);
+
+    let config_signature = function_signature(config);
+    let source_signature = function_signature(source);
+    if config_signature != source_signature {
+        differences.push(Difference::Changed {
+            what: "signature",
+            path: path.clone(),
+            config: config_signature,
+            source: source_signature
+        });
+    }
+
+    diff_decorators(&config.decorators, &source.decorators, &path, differences);
+
+    if config.description != source.description {
+        differences.push(Difference::Changed {
+            what: "docstring",
+            path: path,
+            config: description(&config.description),
+            source: description(&source.description)
+        });
+    }
+}
+
+fn attribute(attribute: &Attribute) -> String {
+    match attribute.origin {
+        AttributeOrigin::Class => attribute.to_source(),
+        AttributeOrigin::Instance => format!("self.{}", attribute.to_source())
+    }
+}
+
+fn diff_attribute(config: &Attribute, source: &Attribute, path: &str, differences: &mut Vec<Difference>) {
+    if config != source {
+        differences.push(Difference::Changed {
+            what: "attribute",
+            path: dotted_path(path, &config.name),
+            config: attribute(config),
+            source: attribute(source)
+        });
+    }
+}
+
+fn diff_class(config: &Class, source: &Class, path: &str, differences: &mut Vec<Difference>) {
+    let path = dotted_path(path, &config.name);
+
+    if config.parents != source.parents {
+        differences.push(Difference::Changed {
+            what: "bases",
+            path: path.clone(),
+            config: format!("({})", config.parents.join(", ")),
+            source: format!("({})", source.parents.join(", "))
+        });
+    }
+
+    diff_decorators(&config.decorators, &source.decorators, &path, differences);
+
+    if config.description != source.description {
+        differences.push(Difference::Changed {
+            what: "docstring",
+            path: path.clone(),
+            config: description(&config.description),
+            source: description(&source.description)
+        });
+    }
+
+    diff_named(&config.attributes, &source.attributes, "attribute", &path, |x| &x.name,
+               |c, s, d| diff_attribute(c, s, &path, d), differences);
+    diff_named(&config.methods, &source.methods, "method", &path, |x| &x.name,
+               |c, s, d| diff_function(c, s, &path, d), differences);
+    diff_named(&config.classes, &source.classes, "class", &path, |x| &x.name,
+               |c, s, d| diff_class(c, s, &path, d), differences);
+}
+
+fn diff_variable(config: &Variable, source: &Variable, path: &str, differences: &mut Vec<Difference>) {
+    if config != source {
+        differences.push(Difference::Changed {
+            what: "variable",
+            path: dotted_path(path, &config.name),
+            config: config.to_source(),
+            source: source.to_source()
+        });
+    }
+}
+
+fn diff_module(config: &Module, source: &Module, path: &str, differences: &mut Vec<Difference>) {
+    let path = dotted_path(path, &config.name);
+
+    if config.description != source.description {
+        differences.push(Difference::Changed {
+            what: "docstring",
+            path: path.clone(),
+            config: description(&config.description),
+            source: description(&source.description)
+        });
+    }
+
+    let mut config_imports = config.imports.iter().map(|x| x.to_source()).collect::<Vec<_>>();
+    let mut source_imports = source.imports.iter().map(|x| x.to_source()).collect::<Vec<_>>();
+    config_imports.sort();
+    source_imports.sort();
+    if config_imports != source_imports {
+        differences.push(Difference::Changed {
+            what: "imports",
+            path: path.clone(),
+            config: config_imports.join("; "),
+            source: source_imports.join("; ")
+        });
+    }
+
+    if config.exports != source.exports {
+        differences.push(Difference::Changed {
+            what: "exports",
+            path: path.clone(),
+            config: format!("{:?}", config.exports),
+            source: format!("{:?}", source.exports)
+        });
+    }
+
+    diff_named(&config.variables, &source.variables, "variable", &path, |x| &x.name,
+               |c, s, d| diff_variable(c, s, &path, d), differences);
+    diff_named(&config.classes, &source.classes, "class", &path, |x| &x.name,
+               |c, s, d| diff_class(c, s, &path, d), differences);
+    diff_named(&config.functions, &source.functions, "function", &path, |x| &x.name,
+               |c, s, d| diff_function(c, s, &path, d), differences);
+}
+
+fn diff_package(config: &Package, source: &Package, path: &str, differences: &mut Vec<Difference>) {
+    let path = dotted_path(path, &config.name);
+
+    diff_named(&config.modules, &source.modules, "module", &path, |x| &x.name,
+               |c, s, d| diff_module(c, s, &path, d), differences);
+    diff_named(&config.packages, &source.packages, "package", &path, |x| &x.name,
+               |c, s, d| diff_package(c, s, &path, d), differences);
+}
+
+/// Compare the config against the parsed python source.
+/// The names of the roots are not compared, the root of a parsed
+/// source is the directory it was parsed from.
+pub fn check(config: &Root, source: &Root) -> Vec<Difference> {
+    let mut differences = Vec::new();
+
+    diff_named(&config.modules, &source.modules, "module", "", |x| &x.name,
+               |c, s, d| diff_module(c, s, "", d), &mut differences);
+    diff_named(&config.packages, &source.packages, "package", "", |x| &x.name,
+               |c, s, d| diff_package(c, s, "", d), &mut differences);
+
+    differences
+}
+
+#[test]
+fn test_check_differences() {
+    use structures::Parameter;
+
+    let config = Root {
+        name: "root".to_string(),
+        packages: Vec::new(),
+        modules: vec![Module {
+            name: "api".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: vec![Function {
+                name: "create_api".to_string(),
+                description: Some("Create the api.".to_string()),
+                parameters: vec![Parameter::new("id")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }, Function {
+                name: "delete_api".to_string(),
+                description: None,
+                parameters: Vec::new(),
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }],
+            classes: Vec::new()
+        }]
+    };
+    let source = Root {
+        name: "root".to_string(),
+        packages: Vec::new(),
+        modules: vec![Module {
+            name: "api".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: vec![Function {
+                name: "create_api".to_string(),
+                description: Some("Create an api.".to_string()),
+                parameters: vec![Parameter::new("id"), Parameter::new("new")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }, Function {
+                name: "helper".to_string(),
+                description: None,
+                parameters: Vec::new(),
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }],
+            classes: Vec::new()
+        }]
+    };
+
+    assert_eq!(check(&config, &config), Vec::new());
+    assert_eq!(check(&config, &source), vec![
+        Difference::Changed {
+            what: "signature",
+            path: "api.create_api".to_string(),
+            config: "(id)".to_string(),
+            source: "(id, new)".to_string()
+        },
+        Difference::Changed {
+            what: "docstring",
+            path: "api.create_api".to_string(),
+            config: "\"Create the api.\"".to_string(),
+            source: "\"Create an api.\"".to_string()
+        },
+        Difference::Missing {kind: "function", path: "api.delete_api".to_string()},
+        Difference::Extra {kind: "function", path: "api.helper".to_string()},
+    ]);
+}
@@ -1,22 +1,77 @@
 use std::env;
+use std::path::Path;
 
-use clap::{Arg, App, SubCommand};
+use clap::{Arg, App, ArgMatches, SubCommand, Error, ErrorKind};
 
 const ABOUT: &'static str = "
 x-ray generates python code from a configuration file and vice versa.
 ";
 
+/// The conf file given as `-f -` is read from stdin or written to stdout.
+pub const STDIO: &'static str = "-";
+
+const CONF_TYPES: [&'static str; 4] = ["toml", "yaml", "yml", "json"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ConfType {
     Toml,
-    Yaml
+    Yaml,
+    Json
+}
+
+impl ConfType {
+    /// The config type for a name like `yaml`, which is also the extension
+    /// of the conf file.
+    pub fn from_name(name: &str) -> Option<ConfType> {
+        match name {
+            "toml" => Some(ConfType::Toml),
+            "yaml" | "yml" => Some(ConfType::Yaml),
+            "json" => Some(ConfType::Json),
+            _ => None
+        }
+    }
+
+    /// The extension of a conf file of this type.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            ConfType::Toml => "toml",
+            ConfType::Yaml => "yaml",
+            ConfType::Json => "json"
+        }
+    }
+
+    /// The config type for the extension of a conf file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ConfType> {
+        path.as_ref().extension()
+            .and_then(|x| x.to_str())
+            .and_then(|x| ConfType::from_name(&x.to_lowercase()))
+    }
+}
+
+/// Order of the parsed packages, modules, classes and functions.
+/// Packages and modules are always sorted by name, `Declaration` keeps
+/// the order of the classes and functions inside a file.
+#[derive(Clone, Copy)]
+pub enum Order {
+    Name,
+    Declaration
 }
 
 pub struct CliConf {
     pub skip_validations: bool,
+    pub merge: bool,
+    pub stubs: bool,
+    pub tests: bool,
     pub conf_file: Option<String>,
     pub conf_type: ConfType,
     pub parse: bool,
+    pub split: bool,
+    pub check: bool,
+    pub schema: bool,
+    pub migrate: bool,
+    pub output_file: Option<String>,
     pub parse_dir: Option<String>,
+    pub order: Order,
     pub gen_dir: Option<String>,
 }
 
@@ -42,16 +97,26 @@ pub fn main() -> CliConf {
                 .short("s")
                 .long("skip-validations")
                 .help("Skip any python validations"))
+            .arg(Arg::with_name("merge")
+                .short("m")
+                .long("merge")
+                .help("Merge into existing python files instead of overwriting them"))
+            .arg(Arg::with_name("stubs")
+                .long("stubs")
+                .help("Also write a .pyi stub for every module"))
+            .arg(Arg::with_name("tests")
+                .long("tests")
+                .help("Also write pytest modules to a tests directory, existing ones are kept"))
             .arg(Arg::with_name("conf_file")
                 .short("f")
                 .value_name("conf_file")
                 .required(true)
-                .help("Provide the conf file"))
+                .help("Provide the conf file, - reads json from stdin"))
             .arg(Arg::with_name("conf_type")
                 .short("t")
                 .value_name("conf_type")
-                .required(true)
-                .help("Provide the config type (Toml/Yaml)"))
+                .possible_values(&CONF_TYPES)
+                .help("Provide the config type, by default it follows the extension of the conf file"))
             .arg(Arg::with_name("dir")
                 .short("d")
                 .value_name("dir")
@@ -67,20 +132,71 @@ pub fn main() -> CliConf {
                 .short("f")
                 .value_name("conf_file")
                 .required(true)
-                .help("Provide the name of the conf file "))
+                .help("Provide the name of the conf file, - writes json to stdout"))
             .arg(Arg::with_name("conf_type")
                 .short("t")
                 .value_name("conf_type")
+                .possible_values(&CONF_TYPES)
+                .help("Provide the config type, by default it follows the extension of the conf file"))
+            .arg(Arg::with_name("keep_order")
+                .long("keep-order")
+                .help("Keep the declaration order of classes and functions instead of sorting them by name"))
+            .arg(Arg::with_name("split")
+                .long("split")
+                .help("Write every top level package to a file of its own, which the conf file includes")))
+        .subcommand(SubCommand::with_name("check")
+            .about("check that python source and conf file have the same structure")
+            .arg(Arg::with_name("dir")
+                .short("d")
+                .value_name("dir")
                 .required(true)
-                .help("Provide the config type (Toml/Yaml)")));
+                .help("Provide the path of python project"))
+            .arg(Arg::with_name("conf_file")
+                .short("f")
+                .value_name("conf_file")
+                .required(true)
+                .help("Provide the conf file, - reads json from stdin"))
+            .arg(Arg::with_name("conf_type")
+                .short("t")
+                .value_name("conf_type")
+                .possible_values(&CONF_TYPES)
+                .help("Provide the config type, by default it follows the extension of the conf file")))
+        .subcommand(SubCommand::with_name("migrate")
+            .about("rewrite a conf file of an older version in the current version")
+            .arg(Arg::with_name("conf_file")
+                .short("f")
+                .value_name("conf_file")
+                .required(true)
+                .help("Provide the conf file, - reads json from stdin"))
+            .arg(Arg::with_name("conf_type")
+                .short("t")
+                .value_name("conf_type")
+                .possible_values(&CONF_TYPES)
+                .help("Provide the config type, by default it follows the extension of the conf file"))
+            .arg(Arg::with_name("output_file")
+                .short("o")
+                .value_name("output_file")
+                .help("Provide the file to write the migrated config to, by default the conf file is rewritten")))
+        .subcommand(SubCommand::with_name("schema")
+            .about("write the JSON Schema of the conf file")
+            .arg(Arg::with_name("schema_file")
+                .short("f")
+                .value_name("schema_file")
+                .help("Provide the name of the schema file, by default it's written to stdout")));
 
     let matches = app.get_matches();
 
     let mut skip_validations: bool = false;
+    let mut merge: bool = false;
+    let mut stubs: bool = false;
+    let mut tests: bool = false;
     let mut conf_file = "";
     let mut conf_type = ConfType::Toml;
     let mut parse_dir = None;
+    let mut order = Order::Name;
+    let mut split = false;
     let mut gen_dir = Some(get_current_directory());
+    let mut output_file = None;
 
     if let Some(matches) = matches.subcommand_matches("gen") {
         if matches.is_present("skip_validations") {
@@ -88,15 +204,21 @@ pub fn main() -> CliConf {
             skip_validations = true;
         }
 
-        conf_file = matches.value_of("conf_file").unwrap();
-        let conf_type_str = matches.value_of("conf_type").unwrap();
+        if matches.is_present("merge") {
+            merge = true;
+        }
+
+        if matches.is_present("stubs") {
+            stubs = true;
+        }
 
-        if conf_type_str == "toml" {
-            conf_type = ConfType::Toml;
-        } else if conf_type_str == "yaml" {
-            conf_type = ConfType::Yaml;
+        if matches.is_present("tests") {
+            tests = true;
         }
 
+        conf_file = matches.value_of("conf_file").unwrap();
+        conf_type = conf_type_of(matches);
+
         gen_dir = Some(matches.value_of("dir").unwrap().to_string());
     }
 
@@ -107,29 +229,110 @@ pub fn main() -> CliConf {
 
     if let Some(matches) = matches.subcommand_matches("parse") {
         parse_dir = Some(matches.value_of("dir").unwrap().to_string());
+
+        if matches.is_present("keep_order") {
+            order = Order::Declaration;
+        }
+
         conf_file = matches.value_of("conf_file").unwrap();
-        let conf_type_str = matches.value_of("conf_type").unwrap();
+        conf_type = conf_type_of(matches);
 
-        if conf_type_str == "toml" {
-            conf_type = ConfType::Toml;
-        } else if conf_type_str == "yaml" {
-            conf_type = ConfType::Yaml;
+        if matches.is_present("split") {
+            if conf_file == STDIO {
+                Error::with_description("--split writes files next to the conf file, it can't be used with -f -",
+                                        ErrorKind::ArgumentConflict).exit();
+            }
+            split = true;
         }
     }
 
+    let check = match matches.subcommand_matches("check") {
+        Some(_) => true,
+        None => false,
+    };
+
+    if let Some(matches) = matches.subcommand_matches("check") {
+        parse_dir = Some(matches.value_of("dir").unwrap().to_string());
+        conf_file = matches.value_of("conf_file").unwrap();
+        conf_type = conf_type_of(matches);
+    }
+
+    let migrate = match matches.subcommand_matches("migrate") {
+        Some(_) => true,
+        None => false,
+    };
+
+    if let Some(matches) = matches.subcommand_matches("migrate") {
+        conf_file = matches.value_of("conf_file").unwrap();
+        conf_type = conf_type_of(matches);
+        output_file = matches.value_of("output_file").map(|x| x.to_string());
+    }
+
+    let schema = match matches.subcommand_matches("schema") {
+        Some(_) => true,
+        None => false,
+    };
+
+    if let Some(matches) = matches.subcommand_matches("schema") {
+        conf_file = matches.value_of("schema_file").unwrap_or(STDIO);
+    }
+
     let cli_conf: CliConf = CliConf {
         skip_validations: skip_validations,
+        merge: merge,
+        stubs: stubs,
+        tests: tests,
         conf_file: Some(conf_file.to_string()),
         conf_type: conf_type,
         parse: parse,
+        split: split,
+        check: check,
+        schema: schema,
+        migrate: migrate,
+        output_file: output_file,
         parse_dir: parse_dir,
+        order: order,
         gen_dir: gen_dir
     };
 
     return cli_conf;
 }
 
+/// The config type given with -t, or else the one of the conf file extension.
+/// Stdin and stdout are json unless -t says otherwise.
+fn conf_type_of(matches: &ArgMatches) -> ConfType {
+    // -t only accepts the CONF_TYPES.
+    if let Some(name) = matches.value_of("conf_type") {
+        return ConfType::from_name(name).unwrap();
+    }
+
+    let conf_file = matches.value_of("conf_file").unwrap();
+    if conf_file == STDIO {
+        return ConfType::Json;
+    }
+
+    match ConfType::from_path(conf_file) {
+        Some(conf_type) => conf_type,
+        None => {
+            let message = format!("Unable to tell the config type of '{}' from its extension, use -t {}",
+                                  conf_file, CONF_TYPES.join("|"));
+            Error::with_description(&message, ErrorKind::InvalidValue).exit()
+        }
+    }
+}
+
 fn get_current_directory() -> String {
     let cwd = env::current_dir().unwrap();
     cwd.to_str().unwrap().to_string()
 }
+
+#[test]
+fn test_conf_type_from_path() {
+    assert_eq!(ConfType::from_path("conf/project.toml"), Some(ConfType::Toml));
+    assert_eq!(ConfType::from_path("project.yaml"), Some(ConfType::Yaml));
+    assert_eq!(ConfType::from_path("project.YML"), Some(ConfType::Yaml));
+    assert_eq!(ConfType::from_path("project.json"), Some(ConfType::Json));
+    assert_eq!(ConfType::from_path("project.ini"), None);
+    assert_eq!(ConfType::from_path("project"), None);
+    assert_eq!(ConfType::from_path(STDIO), None);
+}
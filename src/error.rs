@@ -0,0 +1,147 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::result;
+
+use toml;
+use serde_yaml;
+use serde_json;
+
+/// Errors returned by the x-ray library.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io {path: PathBuf, error: io::Error},
+
+    /// The config file could not be deserialized.
+    /// Line and column start at 1, when they are known.
+    Config {path: PathBuf, line: Option<usize>, column: Option<usize>, message: String},
+
+    /// The parsed result could not be serialized into a config.
+    Serialize {message: String},
+
+    /// A python source file could not be parsed.
+    /// Offset is the byte offset where parsing stopped.
+    Parse {file: PathBuf, offset: usize},
+
+    /// A python source file mixes tabs and spaces in a way that makes
+    /// the meaning of its indentation depend on the tab size.
+    Indentation {file: PathBuf, line: usize},
+
+    /// The config did not pass the validations.
+    Validation {violations: Vec<Violation>},
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// A validation rule broken by an item in the config.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Violation {
+    /// Dotted path of the item, e.g. `sample.display.Animal.get_animal`.
+    pub path: String,
+    pub rule: String,
+}
+
+impl Violation {
+    pub fn new(path: String, rule: &str) -> Violation {
+        Violation {
+            path: path,
+            rule: rule.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.rule)
+    }
+}
+
+impl Error {
+    pub fn io<P: Into<PathBuf>>(path: P, error: io::Error) -> Error {
+        Error::Io {
+            path: path.into(),
+            error: error
+        }
+    }
+
+    pub fn toml<P: Into<PathBuf>>(path: P, error: toml::de::Error) -> Error {
+        let (line, column) = match error.line_col() {
+            // toml counts from 0.
+            Some((line, column)) => (Some(line + 1), Some(column + 1)),
+            None => (None, None)
+        };
+
+        Error::Config {
+            path: path.into(),
+            line: line,
+            column: column,
+            message: error.to_string()
+        }
+    }
+
+    pub fn json<P: Into<PathBuf>>(path: P, error: serde_json::Error) -> Error {
+        // serde_json counts from 1, 0 means there is no position.
+        let (line, column) = match (error.line(), error.column()) {
+            (0, _) => (None, None),
+            (line, column) => (Some(line), Some(column))
+        };
+
+        Error::Config {
+            path: path.into(),
+            line: line,
+            column: column,
+            message: error.to_string()
+        }
+    }
+
+    pub fn yaml<P: Into<PathBuf>>(path: P, error: serde_yaml::Error) -> Error {
+        let (line, column) = match error.location() {
+            Some(location) => (Some(location.line()), Some(location.column())),
+            None => (None, None)
+        };
+
+        Error::Config {
+            path: path.into(),
+            line: line,
+            column: column,
+            message: error.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io {ref path, ref error} => write!(f, "{}: {}", path.display(), error),
+            Error::Config {ref path, line: Some(line), column: Some(column), ref message} => {
+                write!(f, "{}:{}:{}: {}", path.display(), line, column, message)
+            },
+            Error::Config {ref path, ref message, ..} => write!(f, "{}: {}", path.display(), message),
+            Error::Serialize {ref message} => write!(f, "Unable to serialize config: {}", message),
+            Error::Parse {ref file, offset} => {
+                write!(f, "{}: Unable to parse python source at byte {}", file.display(), offset)
+            },
+            Error::Indentation {ref file, line} => {
+                write!(f, "{}:{}: inconsistent use of tabs and spaces in indentation", file.display(), line)
+            },
+            Error::Validation {ref violations} => {
+                write!(f, "{} validation error(s)", violations.len())?;
+                for violation in violations {
+                    write!(f, "\n  {}", violation)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io {ref error, ..} => Some(error),
+            _ => None
+        }
+    }
+}
@@ -1,104 +1,934 @@
 use std::path::{Path, PathBuf};
 use std::fs;
-
-use toml;
-use serde_yaml;
+use std::result;
 
 use cli::ConfType;
-use util::{read_file, write_to_file, create_package};
-use template::{module_desc_template, class_template, function_template};
-use structures::{Config, Root, Package, Module, Validate};
+use error::{Error, Result};
+use util::{read_config, read_module, read_settings, write_to_file, write_stub_to_file, create_package, module_exists};
+use template::{module_desc_template, imports_template, exports_template, variables_template, class_template, function_template, method_template};
+use template::{indent, init_method, init_template, variables_stub, class_stub, function_stub, pytest_class_template, pytest_function_template};
+use structures::{Package, Module, Class, Function, Import, Attribute, AttributeOrigin};
+#[cfg(test)]
+use structures::{Parameter, Variable};
+use validate::{validate, Rules};
+use parser;
+use parser::ItemKind;
 
-fn validate (root: &Root) {
-    for package in &root.packages {
-        let ref modules: Vec<Module> = package.modules;
+/// Options of the code generation, all of them are off by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenOptions {
+    /// Generate the code even if names break the naming rules.
+    pub skip_validations: bool,
+    /// Merge into the python files that already exist instead of overwriting them.
+    pub merge: bool,
+    /// Write a `.pyi` stub next to every module.
+    pub stubs: bool,
+    /// Write a pytest module for every module to the `tests` directory.
+    /// Existing test modules are kept.
+    pub tests: bool,
+}
 
-        for module in modules {
-            let ref functions = module.functions;
-            let ref classes = module.classes;
+fn generate_package_src(packages: Vec<Package>, package_path: &Path, options: &GenOptions) -> Result<()> {
+    for package in packages {
+        let package_path = package_path.join(package.name);
+        create_package(&package_path)?;
 
-            for function in functions {
-                let is_valid: bool = function.validate_case();
+        generate_package_src(package.packages, &package_path, options)?;
 
-                if !is_valid {
-                    panic!("Invalid function name format");
-                }
-            }
+        let modules = package.modules;
 
-            for class in classes {
-                let is_valid: bool = class.validate_case();
+        generate_module_src(modules, &package_path, options)?;
+    }
 
-                if !is_valid {
-                    panic!("Invalid class name format");
-                }
-            }
+    Ok(())
+}
+
+fn generate_module_src(modules: Vec<Module>, path: &Path, options: &GenOptions) -> Result<()> {
+    for module in modules {
+        let filename = module.name.clone();
+
+        // Stubs only hold what is in the config, so they are always rewritten.
+        if options.stubs {
+            write_stub_to_file(&path, &filename, &module_stub(&module))?;
         }
+
+        let existing = if options.merge {
+            read_module(&path, &filename)?
+        } else {
+            None
+        };
+
+        let content = match existing {
+            Some(existing) => {
+                let file = path.join(filename.clone() + ".py");
+                merge_module_src(module, &existing).map_err(|offset| Error::Parse {file: file, offset: offset})?
+            },
+            None => module_src(module)
+        };
+
+        write_to_file(&path, &filename, &content)?;
     }
+
+    Ok(())
 }
 
-fn generate_package_src(packages: Vec<Package>, package_path: &Path) {
-    for package in packages {
-        let package_path = package_path.join(package.name);
-        create_package(&package_path);
+fn module_src(module: Module) -> String {
+    let mut content = String::new();
 
-        generate_package_src(package.packages, &package_path);
+    if let Some(desc) = module.description {
+        content += &module_desc_template(desc);
+    }
 
-        let modules = package.modules;
+    // Imports go right after the doc string, which has to come first.
+    if !module.imports.is_empty() {
+        content += &imports_template(&module.imports);
+    }
+
+    if !module.exports.is_empty() {
+        content += &exports_template(&module.exports);
+    }
+
+    if !module.variables.is_empty() {
+        content += &variables_template(&module.variables);
+    }
+
+    for class in module.classes {
+        content += &class_template(class);
+    }
 
-        generate_module_src(modules, &package_path);
+    for function in module.functions {
+        content += &function_template(function);
     }
+
+    content
 }
 
-fn generate_module_src(modules: Vec<Module>, path: &Path) {
+/// The pytest module for a module, `path` is the dotted path of the module.
+/// The functions and classes of the module are imported.
+fn test_module_src(module: &Module, path: &str) -> String {
+    let mut content = module_desc_template(format!("Tests for the {} module.", path));
+
+    let mut names: Vec<&str> = module.classes.iter().map(|x| &x.name[..]).collect();
+    for function in &module.functions {
+        if !names.contains(&&function.name[..]) {
+            names.push(&function.name);
+        }
+    }
+
+    let imports: Vec<Import> = names.iter().map(|name| Import {
+        module: Some(path.to_string()),
+        name: name.to_string(),
+        alias: None,
+        level: 0
+    }).collect();
+
+    if !imports.is_empty() {
+        content += &imports_template(&imports);
+    }
+
+    for class in &module.classes {
+        content += &pytest_class_template(class, "");
+    }
+
+    let mut tests: Vec<String> = Vec::new();
+    for function in &module.functions {
+        let test = pytest_function_template(function);
+        // Overloads share a test.
+        if !tests.contains(&test) {
+            tests.push(test);
+        }
+    }
+
+    content + &tests.concat()
+}
+
+/// Generate a test module for every module, in a tree that mirrors the
+/// packages. `path` is the dotted path of the package, empty for the root.
+/// Test modules that already exist are left as they are.
+fn generate_tests(modules: &[Module], packages: &[Package], tests_path: &Path, path: &str) -> Result<()> {
+    create_package(tests_path)?;
+
     for module in modules {
-        let functions = module.functions;
-        let ref filename = module.name;
+        let filename = format!("test_{}", module.name);
+        if module_exists(tests_path, &filename) {
+            continue;
+        }
+
+        let module_path = if path.is_empty() { module.name.clone() } else { format!("{}.{}", path, module.name) };
+        write_to_file(tests_path, &filename, &test_module_src(module, &module_path))?;
+    }
+
+    for package in packages {
+        let package_path = if path.is_empty() { package.name.clone() } else { format!("{}.{}", path, package.name) };
+        generate_tests(&package.modules, &package.packages, &tests_path.join(&package.name), &package_path)?;
+    }
+
+    Ok(())
+}
+
+/// Check if any of the functions, or the methods of the classes, is decorated with `overload`.
+fn has_overload(functions: &[Function], classes: &[Class]) -> bool {
+    functions.iter().any(|x| x.decorators.iter().any(|d| d == "overload")) ||
+        classes.iter().any(|x| has_overload(&x.methods, &x.classes))
+}
 
-        let classes = module.classes;
-        let mut content = String::new();
+/// The PEP 484 stub of a module, with `...` in place of bodies and values.
+/// `overload` is imported from typing when a decorator uses it.
+fn module_stub(module: &Module) -> String {
+    let mut content = String::new();
 
-        match module.description {
-            Some(desc) => {
-                content += &module_desc_template(desc);
-                write_to_file(&path, &filename, &content);
+    let mut imports = module.imports.clone();
+    if has_overload(&module.functions, &module.classes) && !imports.iter().any(|x| x.name_source() == "overload") {
+        imports.push(Import {
+            module: Some("typing".to_string()),
+            name: "overload".to_string(),
+            alias: None,
+            level: 0
+        });
+    }
+
+    if !imports.is_empty() {
+        content += &imports_template(&imports);
+    }
+
+    if !module.exports.is_empty() {
+        content += &exports_template(&module.exports);
+    }
+
+    if !module.variables.is_empty() {
+        content += &variables_stub(&module.variables);
+    }
+
+    for class in &module.classes {
+        content += &class_stub(class);
+    }
+
+    for function in &module.functions {
+        content += &function_stub(function);
+    }
+
+    // The file starts with the first statement, there is no doc string before it.
+    content.trim_left().to_string()
+}
+
+/// The span of an item without the empty lines around it.
+fn trimmed_span(source: &str, item: &parser::SpannedItem) -> (usize, usize) {
+    let item_src = &source[item.start..item.end];
+    let end = item.start + item_src.trim_right().len();
+
+    (end - item_src.trim().len(), end)
+}
+
+/// The lines of the source between `start` and `end`, with their offsets.
+fn source_lines(source: &str, start: usize, end: usize) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut offset = start;
+    for line in source[start..end].split('\n') {
+        lines.push((offset, line));
+        offset += line.len() + 1;
+    }
+
+    lines
+}
+
+/// The index of the `class` line of a class, after its decorators, and the
+/// line that starts its body, the first one after it that is not empty.
+fn class_body<'a>(lines: &[(usize, &'a str)]) -> Option<(usize, &'a str)> {
+    let header = lines.iter().position(|&(_, line)| line.trim_left().starts_with("class "))?;
+    let body = lines[header + 1..].iter().find(|&&(_, line)| !line.trim().is_empty())?;
+
+    Some((header, body.1))
+}
+
+/// The span of the class `name` in the body of the class at `start..end`
+/// of the source. The body ends at the next line that is not indented
+/// deeper than the class. Indentation is compared in columns, like python.
+fn nested_class_span(source: &str, start: usize, end: usize, name: &str) -> Option<(usize, usize)> {
+    let width = |line: &str| parser::indentation_width(line.as_bytes());
+    let is_blank = |line: &str| line.trim().is_empty();
+
+    let lines = source_lines(source, start, end);
+    let (header, body) = class_body(&lines)?;
+    let body_width = width(body);
+
+    let class_header = format!("class {}", name);
+    let nested_header = lines[header + 1..].iter().position(|&(_, line)| {
+        let statement = line.trim_left();
+        width(line) == body_width && statement.starts_with(&class_header) &&
+            statement[class_header.len()..].trim_left().starts_with(&['(', ':'][..])
+    })? + header + 1;
+
+    let nested_end = lines[nested_header + 1..].iter()
+        .find(|&&(_, line)| !is_blank(line) && width(line) <= body_width)
+        .map_or(end, |&(offset, _)| offset);
+
+    Some((lines[nested_header].0, nested_end))
+}
+
+/// Indent source generated for the body of a top level class, with 4 spaces
+/// per level, for a class body indented with `body_indent`. Every further
+/// level adds `unit`, so tabs stay tabs.
+fn reindent(source: &str, body_indent: &str, unit: &str) -> String {
+    source.split('\n')
+        .map(|line| {
+            let levels = (line.len() - line.trim_left_matches(' ').len()) / 4;
+            if levels == 0 {
+                line.to_string()
+            } else {
+                format!("{}{}{}", body_indent, unit.repeat(levels - 1), &line[levels * 4..])
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Add the attributes, methods and nested classes missing from the existing
+/// class at `span` of the source. The new code is indented like the body of
+/// the class. Class attributes go before the first method or nested class,
+/// instance attributes to the end of `__init__`, which is added when the
+/// class doesn't have one.
+fn merge_class(class: Class, attributes: &[Attribute], methods: &[Function], classes: &[Class], source: &str, span: (usize, usize), edits: &mut Vec<(usize, usize, String)>) {
+    let (start, end) = span;
+
+    let lines = source_lines(source, start, end);
+    let leading = |line: &str| line[..line.len() - line.trim_left().len()].to_string();
+    let width = |line: &str| parser::indentation_width(line.as_bytes());
+    let (header, header_indent, body_indent) = match class_body(&lines) {
+        Some((header, body)) => (header, leading(lines[header].1), leading(body)),
+        None => (0, String::new(), "    ".to_string())
+    };
+    let unit = if body_indent.starts_with(&header_indent) && body_indent != header_indent {
+        body_indent[header_indent.len()..].to_string()
+    } else {
+        body_indent.clone()
+    };
+    let body_width = width(&body_indent);
+
+    // The lines of the class body itself, not of its methods and nested classes.
+    let body_lines: Vec<(usize, &str)> = lines[header + 1..].iter()
+        .filter(|&&(_, line)| !line.trim().is_empty() && width(line) == body_width)
+        .cloned()
+        .collect();
+
+    let (class_attributes, instance_attributes): (Vec<Attribute>, Vec<Attribute>) = class.attributes.into_iter()
+        .filter(|attribute| !attributes.iter().any(|x| x.name == attribute.name))
+        .partition(|x| x.origin == AttributeOrigin::Class);
+
+    let mut content = String::new();
+
+    if !class_attributes.is_empty() {
+        let attributes_src: String = class_attributes.iter().map(|x| format!("    {}\n", x.to_source())).collect();
+
+        let first_definition = body_lines.iter().find(|&&(_, line)| {
+            let statement = line.trim_left();
+            ["def ", "async ", "class ", "@"].iter().any(|x| statement.starts_with(x))
+        });
+        match first_definition {
+            Some(&(offset, _)) => edits.push((offset, offset, reindent(&attributes_src, &body_indent, &unit) + "\n")),
+            None => content += &("\n".to_string() + &attributes_src)
+        }
+    }
+
+    let mut new_methods = class.methods;
+    if !instance_attributes.is_empty() {
+        let init = body_lines.iter().position(|&(_, line)| line.trim_left().starts_with("def __init__("));
+        match init {
+            Some(index) => {
+                let init_end = body_lines.get(index + 1).map_or(end, |&(offset, _)| offset);
+                let position = body_lines[index].0 + source[body_lines[index].0..init_end].trim_right().len();
+
+                let assignments: String = instance_attributes.iter()
+                    .map(|x| format!("\n{}{}self.{}", body_indent, unit, x.to_source()))
+                    .collect();
+                edits.push((position, position, assignments));
             },
-            None => {}
-        };
+            None => {
+                let init = match new_methods.iter().position(|x| x.name == "__init__") {
+                    Some(index) => new_methods.remove(index),
+                    None => init_method()
+                };
+                content += &init_template(init, &instance_attributes);
+            }
+        }
+    }
 
-        for class in classes {
-            content += &class_template(class);
-            write_to_file(&path, &filename, &content);
+    for nested_class in class.classes {
+        match classes.iter().find(|x| x.name == nested_class.name) {
+            Some(existing_class) => {
+                if let Some(nested_span) = nested_class_span(source, start, end, &nested_class.name) {
+                    merge_class(nested_class, &existing_class.attributes, &existing_class.methods, &existing_class.classes, source, nested_span, edits);
+                }
+            },
+            None => content += &indent(&class_template(nested_class))
         }
+    }
 
-        for function in functions {
-            content += &function_template(function);
+    for method in new_methods {
+        if !methods.iter().any(|x| x.name == method.name) {
+            content += &method_template(method);
         }
+    }
 
-        write_to_file(&path, &filename, &content);
+    if !content.is_empty() {
+        let position = start + source[start..end].trim_right().len();
+        edits.push((position, position, "\n".to_string() + reindent(&content, &body_indent, &unit).trim_right()));
     }
 }
 
-pub fn generate(skip_validations: bool, gen_dir: String, conf_file: &str, conf_type: ConfType) {
-    let file_content = read_file(conf_file);
-    let config: Config = match conf_type {
-        ConfType::Toml => toml::from_str(&file_content).unwrap(),
-        ConfType::Yaml => serde_yaml::from_str(&file_content).unwrap()
+/// Add a block of source after the item ending at `position`, separated by
+/// `separator`, or at the top of the file when there is no such item.
+fn insert_block(edits: &mut Vec<(usize, usize, String)>, position: Option<usize>, separator: &str, block: &str) {
+    match position {
+        Some(position) => edits.push((position, position, separator.to_string() + block.trim())),
+        None => edits.push((0, 0, block.trim().to_string() + "\n\n"))
+    }
+}
+
+/// Merge the module config into the source of an existing module.
+///
+/// Everything already in the file is kept as it is, including the bodies of
+/// functions and classes and any code that is not part of the config. Only
+/// the imports, exports, variables, classes, nested classes, methods and
+/// functions missing from the file are added. New imports go after the
+/// existing ones, `__future__` imports before all others, and the missing
+/// exports are added to the existing `__all__`.
+///
+/// Returns the byte offset where parsing stopped if the file could not be
+/// parsed completely, items after it would be added a second time.
+fn merge_module_src(module: Module, existing: &str) -> result::Result<String, usize> {
+    // The parser expects every line, including the last, to end with a newline.
+    let existing = if existing.ends_with('\n') {
+        existing.to_string()
+    } else {
+        existing.to_string() + "\n"
     };
+    let existing = existing.as_str();
+    let spanned_items = parser::parse_spanned(existing.as_bytes())?;
+
+    let mut existing_imports: Vec<Import> = Vec::new();
+    let mut existing_exports: Option<(usize, usize, Vec<String>)> = None;
+    let mut existing_variables: Vec<String> = Vec::new();
+    let mut existing_functions: Vec<String> = Vec::new();
+    let mut existing_classes: Vec<String> = Vec::new();
+
+    // Where the last item of each kind ends.
+    let mut future_end: Option<usize> = None;
+    let mut imports_end: Option<usize> = None;
+    let mut variables_end: Option<usize> = None;
+
+    // (start, end, content) replacing the source between start and end,
+    // start and end are the same for content that is only added.
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut new_classes: Vec<Class> = Vec::new();
+
+    // The doc string, shebang and comments at the top of the file.
+    let body_start = spanned_items.iter()
+        .find(|x| match x.item.node {
+            ItemKind::Shebang {..} => false,
+            ItemKind::Code {ref code} => !code.starts_with('#'),
+            _ => true
+        })
+        .map_or(existing.len(), |x| x.start);
+    let header_end = match existing[..body_start].trim_right().len() {
+        0 => None,
+        end => Some(end)
+    };
+
+    for spanned_item in &spanned_items {
+        let (start, end) = trimmed_span(existing, spanned_item);
+
+        match spanned_item.item.node {
+            ItemKind::Import {ref imports} => {
+                existing_imports.extend(imports.iter().cloned());
+                if imports.iter().all(|x| x.module_source() == "__future__") {
+                    future_end = Some(end);
+                } else {
+                    imports_end = Some(end);
+                }
+            },
+            ItemKind::Exports {ref names} => existing_exports = Some((start, end, names.clone())),
+            ItemKind::Variable {ref variable} => {
+                existing_variables.push(variable.name.clone());
+                variables_end = Some(end);
+            },
+            ItemKind::Function {ref name, ..} => existing_functions.push(name.clone()),
+            ItemKind::Class {ref name, ..} => existing_classes.push(name.clone()),
+            _ => {}
+        }
+    }
+
+    let (new_future_imports, new_imports): (Vec<Import>, Vec<Import>) = module.imports.into_iter()
+        .filter(|import| !existing_imports.contains(import))
+        .partition(|import| import.module_source() == "__future__");
+
+    if !new_future_imports.is_empty() {
+        let separator = if future_end.is_some() { "\n" } else { "\n\n" };
+        insert_block(&mut edits, future_end.or(header_end), separator, &imports_template(&new_future_imports));
+    }
+
+    let imports_position = imports_end.or(future_end).or(header_end);
+    if !new_imports.is_empty() {
+        let separator = if imports_end.is_some() { "\n" } else { "\n\n" };
+        insert_block(&mut edits, imports_position, separator, &imports_template(&new_imports));
+    }
+
+    let exports_position = match existing_exports {
+        Some((start, end, names)) => {
+            let mut names = names;
+            let new_names: Vec<String> = module.exports.into_iter().filter(|x| !names.contains(x)).collect();
+            if !new_names.is_empty() {
+                names.extend(new_names);
+                edits.push((start, end, exports_template(&names).trim().to_string()));
+            }
+            Some(end)
+        },
+        None => {
+            if !module.exports.is_empty() {
+                insert_block(&mut edits, imports_position, "\n\n", &exports_template(&module.exports));
+            }
+            imports_position
+        }
+    };
+
+    let new_variables: Vec<_> = module.variables.into_iter()
+        .filter(|variable| !existing_variables.contains(&variable.name))
+        .collect();
+    if !new_variables.is_empty() {
+        let separator = if variables_end.is_some() { "\n" } else { "\n\n" };
+        insert_block(&mut edits, variables_end.or(exports_position), separator, &variables_template(&new_variables));
+    }
+
+    for class in module.classes {
+        if !existing_classes.contains(&class.name) {
+            new_classes.push(class);
+            continue;
+        }
+
+        for spanned_item in &spanned_items {
+            if let ItemKind::Class {ref name, ref attributes, ref methods, ref classes, ..} = spanned_item.item.node {
+                if *name == class.name {
+                    merge_class(class, attributes, methods, classes, existing, (spanned_item.start, spanned_item.end), &mut edits);
+                    break;
+                }
+            }
+        }
+    }
+
+    edits.sort_by_key(|&(start, _, _)| start);
+
+    let mut content = String::new();
+    let mut last = 0;
+    for (start, end, edit) in edits {
+        content += &existing[last..start];
+        content += &edit;
+        last = end;
+    }
+    content += &existing[last..];
+
+    let new_functions: Vec<Function> = module.functions.into_iter()
+        .filter(|function| !existing_functions.contains(&function.name))
+        .collect();
+
+    if new_classes.is_empty() && new_functions.is_empty() {
+        return Ok(content);
+    }
+
+    let mut content = content.trim_right().to_string() + "\n";
+
+    for class in new_classes {
+        content += &class_template(class);
+    }
+
+    for function in new_functions {
+        content += &function_template(function);
+    }
+
+    Ok(content)
+}
+
+pub fn generate(gen_dir: String, conf_file: &str, conf_type: ConfType, options: &GenOptions) -> Result<()> {
+    let config = read_config(conf_file, conf_type)?;
 
     // Root have packages
     // Packages have modules. They can have nested packages.
     // Modules have functions
     let root = config.root;
 
-    if !skip_validations {
-        validate(&root);
+    if !options.skip_validations {
+        // Rules in the config take precedence over the project settings.
+        let validation = config.validation.unwrap_or_default();
+        if let Err(message) = validation.check() {
+            return Err(Error::Config {path: conf_file.into(), line: None, column: None, message: message});
+        }
+
+        // The settings file sits next to the config, a config read from
+        // stdin has no directory and uses the current one.
+        let conf_dir = Path::new(conf_file).parent().unwrap_or(Path::new(""));
+        let validation = validation.or(read_settings(conf_dir)?.validation);
+        let violations = validate(&root, &Rules::new(validation));
+        if !violations.is_empty() {
+            return Err(Error::Validation {violations: violations});
+        }
     }
 
-    fs::create_dir_all(&gen_dir).expect("Unable to create the provided generate directory");
+    if let Err(e) = fs::create_dir_all(&gen_dir) {
+        return Err(Error::io(gen_dir, e));
+    }
 
     let root_path = PathBuf::from(gen_dir);
     let root_path = root_path.as_path();
 
-    generate_module_src(root.modules, root_path);
-    generate_package_src(root.packages, root_path);
+    if options.tests {
+        generate_tests(&root.modules, &root.packages, &root_path.join("tests"), "")?;
+    }
+
+    generate_module_src(root.modules, root_path, options)?;
+    generate_package_src(root.packages, root_path, options)
+}
+
+#[test]
+fn test_merge_module_src_imports() {
+    let existing = r#""""
+The api module
+"""
+
+from __future__ import annotations
+
+import json
+
+
+def create_api():
+    pass
+"#;
+
+    let expected = r#""""
+The api module
+"""
+
+from __future__ import annotations
+from __future__ import division
+
+import json
+import os
+from typing import Dict
+
+
+def create_api():
+    pass
+"#;
+
+    // Without imports and a doc string the imports go to the top.
+    let expected_at_top = r#"from __future__ import annotations, division
+
+import json
+import os
+from typing import Dict
+
+def create_api():
+    pass
+"#;
+
+    for &(existing, expected) in &[(existing, expected), ("def create_api():\n    pass\n", expected_at_top)] {
+        let module = Module {
+            name: "api".to_string(),
+            description: None,
+            imports: vec![Import {
+                module: None,
+                name: "json".to_string(),
+                alias: None,
+                level: 0
+            }, Import {
+                module: None,
+                name: "os".to_string(),
+                alias: None,
+                level: 0
+            }, Import {
+                module: Some("typing".to_string()),
+                name: "Dict".to_string(),
+                alias: None,
+                level: 0
+            }, Import {
+                module: Some("__future__".to_string()),
+                name: "annotations".to_string(),
+                alias: None,
+                level: 0
+            }, Import {
+                module: Some("__future__".to_string()),
+                name: "division".to_string(),
+                alias: None,
+                level: 0
+            }],
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: Vec::new(),
+            classes: Vec::new()
+        };
+
+        assert_eq!(merge_module_src(module, existing), Ok(expected.to_string()));
+    }
+}
+
+#[test]
+fn test_merge_module_src_exports_and_variables() {
+    let existing = r#"import json
+
+__all__ = ["create_api"]
+
+MAX_APIS = 10
+
+
+def create_api():
+    pass
+"#;
+
+    // The existing value of a variable is kept.
+    let expected = r#"import json
+
+__all__ = ["create_api", "delete_api"]
+
+MAX_APIS = 10
+TIMEOUT = 30
+
+
+def create_api():
+    pass
+"#;
+
+    let existing_without_variables = r#""""
+The api module
+"""
+
+
+def create_api():
+    pass
+"#;
+
+    let expected_variables = r#""""
+The api module
+"""
+
+__all__ = ["create_api", "delete_api"]
+
+MAX_APIS = 20
+TIMEOUT = 30
+
+
+def create_api():
+    pass
+"#;
+
+    for &(existing, expected) in &[(existing, expected), (existing_without_variables, expected_variables)] {
+        let module = Module {
+            name: "api".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: vec!["create_api".to_string(), "delete_api".to_string()],
+            variables: vec![
+                Variable::from_source("MAX_APIS = 20").unwrap(),
+                Variable::from_source("TIMEOUT = 30").unwrap(),
+            ],
+            functions: Vec::new(),
+            classes: Vec::new()
+        };
+
+        assert_eq!(merge_module_src(module, existing), Ok(expected.to_string()));
+    }
+}
+
+#[test]
+fn test_merge_module_src_nested_classes() {
+    let module = Module {
+        name: "api".to_string(),
+        description: None,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        variables: Vec::new(),
+        functions: Vec::new(),
+        classes: vec![Class {
+            name: "Animal".to_string(),
+            description: Some("The Animal class".to_string()),
+            parents: Vec::new(),
+            decorators: Vec::new(),
+            attributes: Vec::new(),
+            methods: vec![Function {
+                name: "get_animal".to_string(),
+                description: None,
+                parameters: vec![Parameter::new("self")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }, Function {
+                name: "set_animal".to_string(),
+                description: None,
+                parameters: vec![Parameter::new("self")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }],
+            classes: vec![Class {
+                name: "Options".to_string(),
+                description: Some("The Options class".to_string()),
+                parents: Vec::new(),
+                decorators: Vec::new(),
+                attributes: Vec::new(),
+                methods: vec![Function {
+                    name: "reset".to_string(),
+                    description: None,
+                    parameters: vec![Parameter::new("self")],
+                    returns: None,
+                    is_async: false,
+                    decorators: Vec::new()
+                }],
+                classes: Vec::new()
+            }, Class {
+                name: "Meta".to_string(),
+                description: Some("The Meta class".to_string()),
+                parents: Vec::new(),
+                decorators: Vec::new(),
+                attributes: Vec::new(),
+                methods: vec![Function {
+                    name: "save".to_string(),
+                    description: None,
+                    parameters: vec![Parameter::new("self")],
+                    returns: None,
+                    is_async: false,
+                    decorators: Vec::new()
+                }],
+                classes: Vec::new()
+            }]
+        }]
+    };
+
+    let existing = r#"class Animal:
+
+    class Options:
+        verbose = True
+
+    def get_animal(self):
+        return 1
+"#;
+
+    assert_eq!(merge_module_src(module, existing), Ok(r#"class Animal:
+
+    class Options:
+        verbose = True
+
+        def reset(self):
+            pass
+
+    def get_animal(self):
+        return 1
+
+    class Meta:
+        """
+        The Meta class
+        """
+
+        def save(self):
+            pass
+
+    def set_animal(self):
+        pass
+"#.to_string()));
+}
+
+#[test]
+fn test_merge_module_src_tab_indented_class() {
+    let module = Module {
+        name: "display".to_string(),
+        description: None,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        variables: Vec::new(),
+        functions: Vec::new(),
+        classes: vec![Class {
+            name: "Animal".to_string(),
+            description: None,
+            parents: Vec::new(),
+            decorators: Vec::new(),
+            attributes: Vec::new(),
+            methods: vec![Function {
+                name: "get_animal".to_string(),
+                description: None,
+                parameters: vec![Parameter::new("self")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }, Function {
+                name: "set_animal".to_string(),
+                description: None,
+                parameters: vec![Parameter::new("self")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            }],
+            classes: vec![Class {
+                name: "Options".to_string(),
+                description: None,
+                parents: Vec::new(),
+                decorators: Vec::new(),
+                attributes: Vec::new(),
+                methods: vec![Function {
+                    name: "reset".to_string(),
+                    description: None,
+                    parameters: vec![Parameter::new("self")],
+                    returns: None,
+                    is_async: false,
+                    decorators: Vec::new()
+                }],
+                classes: Vec::new()
+            }]
+        }]
+    };
+
+    let existing = "class Animal:\n\tdef get_animal(self):\n\t\treturn 1\n\n\tclass Options:\n\t\tverbose = True\n";
+
+    assert_eq!(merge_module_src(module, existing), Ok("class Animal:\n\tdef get_animal(self):\n\t\treturn 1\n\n\
+        \tclass Options:\n\t\tverbose = True\n\n\t\tdef reset(self):\n\t\t\tpass\n\n\
+        \tdef set_animal(self):\n\t\tpass\n".to_string()));
+}
+
+#[test]
+fn test_merge_module_src_attributes() {
+    let module = Module {
+        name: "animals".to_string(),
+        description: None,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        variables: Vec::new(),
+        functions: Vec::new(),
+        classes: vec![Class {
+            name: "Dog".to_string(),
+            description: None,
+            parents: Vec::new(),
+            decorators: Vec::new(),
+            attributes: vec![
+                Attribute::from_source("legs: int = 4", AttributeOrigin::Class).unwrap(),
+                Attribute::from_source("sound = 'woof'", AttributeOrigin::Class).unwrap(),
+                Attribute::from_source("name: str = name", AttributeOrigin::Instance).unwrap(),
+                Attribute::from_source("age: int = 0", AttributeOrigin::Instance).unwrap()
+            ],
+            methods: Vec::new(),
+            classes: Vec::new()
+        }, Class {
+            name: "Cat".to_string(),
+            description: None,
+            parents: Vec::new(),
+            decorators: Vec::new(),
+            attributes: vec![
+                Attribute::from_source("lives = 9", AttributeOrigin::Class).unwrap(),
+                Attribute::from_source("name: str = ''", AttributeOrigin::Instance).unwrap()
+            ],
+            methods: Vec::new(),
+            classes: Vec::new()
+        }]
+    };
+
+    let existing = "class Dog:\n    sound = 'woof'\n\n    def __init__(self, name):\n        self.name = name\n\n    \
+        def bark(self):\n        pass\n\n\nclass Cat:\n    pass\n";
+
+    assert_eq!(merge_module_src(module, existing), Ok("class Dog:\n    sound = 'woof'\n\n    legs: int = 4\n\n    \
+        def __init__(self, name):\n        self.name = name\n        self.age: int = 0\n\n    \
+        def bark(self):\n        pass\n\n\nclass Cat:\n    pass\n\n    lives = 9\n\n    \
+        def __init__(self):\n        self.name: str = ''\n".to_string()));
 }
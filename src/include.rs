@@ -0,0 +1,252 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob;
+
+use util;
+use cli::{ConfType, STDIO};
+use error::{Error, Result};
+use structures::{Config, Root, Package, PackageFile, CONFIG_VERSION};
+
+fn config_error<P: Into<PathBuf>>(path: P, message: String) -> Error {
+    Error::Config {path: path.into(), line: None, column: None, message: message}
+}
+
+/// The directory the includes of a config are relative to.
+/// A config read from stdin is relative to the current directory.
+fn base_dir(conf_file: &str) -> PathBuf {
+    if conf_file == STDIO {
+        return PathBuf::new();
+    }
+
+    Path::new(conf_file).parent().map(|x| x.to_path_buf()).unwrap_or_default()
+}
+
+/// The files matching an include pattern, sorted by path.
+fn include_paths(base_dir: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let pattern_path = base_dir.join(pattern);
+    let pattern = match pattern_path.to_str() {
+        Some(pattern) => pattern,
+        None => return Err(config_error(&pattern_path, "The include path is not valid UTF-8".to_string()))
+    };
+
+    let paths = match glob::glob(pattern) {
+        Ok(paths) => paths,
+        Err(e) => return Err(config_error(pattern, format!("Invalid include pattern: {}", e)))
+    };
+
+    let mut result = Vec::new();
+    for path in paths {
+        match path {
+            Ok(path) => result.push(path),
+            Err(e) => {
+                let path = e.path().to_path_buf();
+                return Err(Error::io(path, e.into()));
+            }
+        }
+    }
+
+    if result.is_empty() {
+        return Err(config_error(pattern, "No file matches the include".to_string()));
+    }
+
+    Ok(result)
+}
+
+/// Add the packages and modules of the included files to the packages.
+/// `included` has the files being included, to stop at include cycles.
+fn resolve_packages(packages: &mut [Package], base_dir: &Path, included: &mut Vec<PathBuf>) -> Result<()> {
+    for package in packages {
+        for pattern in package.include.drain(..).collect::<Vec<_>>() {
+            for path in include_paths(base_dir, &pattern)? {
+                let canonical_path = match fs::canonicalize(&path) {
+                    Ok(canonical_path) => canonical_path,
+                    Err(e) => return Err(Error::io(path, e))
+                };
+
+                if included.contains(&canonical_path) {
+                    return Err(config_error(path, format!("Package {} includes itself", package.name)));
+                }
+
+                let mut package_file = util::read_package_file(&path)?;
+
+                // The includes of an included file are relative to that file.
+                included.push(canonical_path);
+                resolve_packages(&mut package_file.packages, path.parent().unwrap(), included)?;
+                included.pop();
+
+                package.packages.extend(package_file.packages);
+                package.modules.extend(package_file.modules);
+            }
+        }
+
+        resolve_packages(&mut package.packages, base_dir, included)?;
+    }
+
+    Ok(())
+}
+
+/// Add the packages and modules of the files included by the packages of
+/// the root, recursively. Includes are relative to the config file.
+pub fn resolve(root: &mut Root, conf_file: &str) -> Result<()> {
+    let mut included = Vec::new();
+    if conf_file != STDIO {
+        match fs::canonicalize(conf_file) {
+            Ok(path) => included.push(path),
+            Err(e) => return Err(Error::io(conf_file, e))
+        }
+    }
+
+    resolve_packages(&mut root.packages, &base_dir(conf_file), &mut included)
+}
+
+/// Move every top level package of the root to a package file next to the
+/// config, named after the package. The packages of the config include
+/// their file.
+pub fn split(mut root: Root, conf_file: &str, conf_type: ConfType) -> Result<(Config, Vec<(PathBuf, PackageFile)>)> {
+    let conf_path = Path::new(conf_file);
+    let extension = conf_path.extension().and_then(|x| x.to_str()).unwrap_or(conf_type.extension());
+
+    let mut package_files = Vec::new();
+    for package in &mut root.packages {
+        let file_name = format!("{}.{}", package.name, extension);
+        let path = base_dir(conf_file).join(&file_name);
+
+        if path == conf_path {
+            return Err(config_error(conf_file, format!("The package file of {} would overwrite the config", package.name)));
+        }
+
+        package_files.push((path, PackageFile {
+            version: CONFIG_VERSION,
+            packages: package.packages.drain(..).collect(),
+            modules: package.modules.drain(..).collect()
+        }));
+        package.include = vec![file_name];
+    }
+
+    Ok((Config::new(root), package_files))
+}
+
+/// Write the config to `conf_file` and every top level package to a
+/// package file of its own, see `split`.
+pub fn write_split(root: Root, conf_file: &str, conf_type: ConfType) -> Result<()> {
+    let (config, package_files) = split(root, conf_file, conf_type)?;
+
+    for (path, package_file) in package_files {
+        util::write_to_config(&path, util::config_to_string(&package_file, conf_type)?)?;
+    }
+
+    util::write_to_config(conf_file, util::config_to_string(&config, conf_type)?)
+}
+
+#[test]
+fn test_include_split() {
+    use structures::Module;
+
+    let root = Root {
+        name: "project".to_string(),
+        packages: vec![Package {
+            name: "api".to_string(),
+            include: Vec::new(),
+            packages: Vec::new(),
+            modules: vec![Module {
+                name: "api".to_string(),
+                description: None,
+                imports: Vec::new(),
+                exports: Vec::new(),
+                variables: Vec::new(),
+                functions: Vec::new(),
+                classes: Vec::new()
+            }]
+        }, Package {
+            name: "sample".to_string(),
+            include: Vec::new(),
+            packages: Vec::new(),
+            modules: vec![Module {
+                name: "display".to_string(),
+                description: None,
+                imports: Vec::new(),
+                exports: Vec::new(),
+                variables: Vec::new(),
+                functions: Vec::new(),
+                classes: Vec::new()
+            }]
+        }],
+        modules: vec![Module {
+            name: "main".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: Vec::new(),
+            classes: Vec::new()
+        }]
+    };
+    let (config, package_files) = split(root, "conf/project.yml", ConfType::Yaml).unwrap();
+
+    let root = Root {
+        name: "project".to_string(),
+        packages: vec![Package {
+            name: "api".to_string(),
+            include: vec!["api.yml".to_string()],
+            packages: Vec::new(),
+            modules: Vec::new()
+        }, Package {
+            name: "sample".to_string(),
+            include: vec!["sample.yml".to_string()],
+            packages: Vec::new(),
+            modules: Vec::new()
+        }],
+        modules: vec![Module {
+            name: "main".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: Vec::new(),
+            classes: Vec::new()
+        }]
+    };
+    assert_eq!(config, Config::new(root));
+    assert_eq!(package_files, vec![
+        (PathBuf::from("conf/api.yml"), PackageFile {version: CONFIG_VERSION, packages: Vec::new(), modules: vec![Module {
+            name: "api".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: Vec::new(),
+            classes: Vec::new()
+        }]}),
+        (PathBuf::from("conf/sample.yml"), PackageFile {version: CONFIG_VERSION, packages: Vec::new(), modules: vec![Module {
+            name: "display".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: Vec::new(),
+            classes: Vec::new()
+        }]}),
+    ]);
+
+    // A package file would overwrite the config itself.
+    let root = Root {
+        name: "project".to_string(),
+        packages: vec![Package {
+            name: "project".to_string(),
+            include: Vec::new(),
+            packages: Vec::new(),
+            modules: Vec::new()
+        }],
+        modules: vec![Module {
+            name: "main".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: Vec::new(),
+            classes: Vec::new()
+        }]
+    };
+    assert!(split(root, "project.json", ConfType::Json).is_err());
+}
@@ -2,34 +2,84 @@
 extern crate serde_derive;
 extern crate serde;
 extern crate serde_yaml;
+#[macro_use]
+extern crate serde_json;
 extern crate toml;
 extern crate regex;
 extern crate clap;
 #[macro_use]
 extern crate nom;
+extern crate unicode_xid;
+extern crate glob;
 
 pub mod util;
 pub mod cli;
+pub mod error;
 mod template;
 mod structures;
 mod parser;
 mod parse;
 mod gen;
+mod validate;
+mod check;
+mod schema;
+mod migrate;
+mod include;
+
+use cli::{ConfType, Order};
+pub use error::{Error, Result};
+pub use check::Difference;
+pub use gen::GenOptions;
 
-use cli::ConfType;
 
+pub fn gen(gen_dir: String, conf_file: &str, conf_type: ConfType) -> Result<()> {
+    gen_with_options(gen_dir, conf_file, conf_type, &GenOptions::default())
+}
 
-pub fn gen(gen_dir: String, conf_file: &str, conf_type: ConfType) {
-    gen::generate(false, gen_dir, conf_file, conf_type);
+/// Same as `gen`, with control over validation, merging into existing
+/// files, stubs and tests, see `GenOptions`.
+pub fn gen_with_options(gen_dir: String, conf_file: &str, conf_type: ConfType, options: &GenOptions) -> Result<()> {
+    gen::generate(gen_dir, conf_file, conf_type, options)
 }
 
-pub fn parse(parse_dir: &str, conf_type: ConfType) -> String {
-    let root_res = parse::parse(&parse_dir);
+pub fn parse(parse_dir: &str, conf_type: ConfType) -> Result<String> {
+    parse_with_order(parse_dir, conf_type, Order::Name)
+}
 
-    let res: String = match conf_type {
+/// Same as `parse`, with control over the order of the parsed items.
+pub fn parse_with_order(parse_dir: &str, conf_type: ConfType, order: Order) -> Result<String> {
+    let root_res = parse::parse(&parse_dir, order)?;
+
+    match conf_type {
         ConfType::Toml => util::get_toml_result(root_res),
-        ConfType::Yaml => util::get_yaml_result(root_res)
-    };
+        ConfType::Yaml => util::get_yaml_result(root_res),
+        ConfType::Json => util::get_json_result(root_res)
+    }
+}
+
+/// Read a config or a package file of any version and serialize it in the
+/// current version. The result has the format of the config.
+pub fn migrate(conf_file: &str, conf_type: ConfType) -> Result<String> {
+    util::migrate_config(conf_file, conf_type)
+}
+
+/// The JSON Schema of the config file, pretty printed.
+pub fn schema() -> String {
+    serde_json::to_string_pretty(&schema::schema()).unwrap() + "\n"
+}
+
+/// Same as `parse`, but writes the config to `conf_file` and every top
+/// level package to a file of its own next to it, which the config includes.
+pub fn parse_split(parse_dir: &str, conf_file: &str, conf_type: ConfType) -> Result<()> {
+    let root_res = parse::parse(&parse_dir, Order::Name)?;
+    include::write_split(root_res, conf_file, conf_type)
+}
+
+/// Compare the python source in `parse_dir` against the config.
+/// Returns the structural differences, empty if they match.
+pub fn check(parse_dir: &str, conf_file: &str, conf_type: ConfType) -> Result<Vec<Difference>> {
+    let config = util::read_config(conf_file, conf_type)?;
+    let root_res = parse::parse(&parse_dir, Order::Declaration)?;
 
-    res
+    Ok(check::check(&config.root, &root_res))
 }
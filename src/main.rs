@@ -2,12 +2,17 @@
 extern crate serde_derive;
 extern crate serde;
 extern crate serde_yaml;
+#[macro_use]
+extern crate serde_json;
 extern crate toml;
 extern crate regex;
 extern crate clap;
 #[macro_use]
 extern crate nom;
+extern crate unicode_xid;
+extern crate glob;
 
+mod error;
 mod template;
 mod structures;
 mod util;
@@ -15,28 +20,82 @@ mod cli;
 mod parser;
 mod parse;
 mod gen;
+mod validate;
+mod check;
+mod schema;
+mod migrate;
+mod include;
+
+use std::process;
 
 use cli::ConfType;
+use error::Result;
 
 fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let cli_values = cli::main();
-    let skip_validations = cli_values.skip_validations;
+    let gen_options = gen::GenOptions {
+        skip_validations: cli_values.skip_validations,
+        merge: cli_values.merge,
+        stubs: cli_values.stubs,
+        tests: cli_values.tests
+    };
+    let split = cli_values.split;
     let conf_file = cli_values.conf_file.unwrap();
     let conf_type = cli_values.conf_type;
     let parse_opt = cli_values.parse;
+    let check_opt = cli_values.check;
+    let schema_opt = cli_values.schema;
+    let migrate_opt = cli_values.migrate;
+    let output_file = cli_values.output_file;
     let parse_dir = cli_values.parse_dir;
+    let order = cli_values.order;
     let gen_dir = cli_values.gen_dir;
 
     if parse_opt {
-        let root_res = parse::parse(&parse_dir.unwrap());
+        let root_res = parse::parse(&parse_dir.unwrap(), order)?;
+        if split {
+            return include::write_split(root_res, &conf_file, conf_type);
+        }
 
         let res: String = match conf_type {
-            ConfType::Toml => util::get_toml_result(root_res),
-            ConfType::Yaml => util::get_yaml_result(root_res)
+            ConfType::Toml => util::get_toml_result(root_res)?,
+            ConfType::Yaml => util::get_yaml_result(root_res)?,
+            ConfType::Json => util::get_json_result(root_res)?
         };
 
-        util::write_to_config(&conf_file, res);
+        util::write_to_config(&conf_file, res)
+    } else if check_opt {
+        let config = util::read_config(&conf_file, conf_type)?;
+        let root_res = parse::parse(&parse_dir.unwrap(), cli::Order::Declaration)?;
+
+        let differences = check::check(&config.root, &root_res);
+        if differences.is_empty() {
+            println!("Python source matches the conf file");
+            return Ok(());
+        }
+
+        for difference in &differences {
+            println!("{}", difference);
+        }
+        println!("{} difference(s) found", differences.len());
+        process::exit(1);
+    } else if migrate_opt {
+        let res = util::migrate_config(&conf_file, conf_type)?;
+        util::write_to_config(output_file.unwrap_or(conf_file), res)
+    } else if schema_opt {
+        let res = serde_json::to_string_pretty(&schema::schema()).unwrap() + "\n";
+        util::write_to_config(&conf_file, res)
     } else {
-        gen::generate(skip_validations, gen_dir.unwrap(), &conf_file, conf_type);
+        let gen_dir = gen_dir.unwrap();
+        gen::generate(gen_dir.clone(), &conf_file, conf_type, &gen_options)?;
+        println!("Python source generated in {}", gen_dir);
+        Ok(())
     }
 }
@@ -0,0 +1,144 @@
+use serde_json;
+use serde_json::Value;
+
+use structures::{Parameter, CONFIG_VERSION};
+
+/// The version of a config that is not deserialized yet.
+/// Configs without a version are version 1.
+pub fn version(config: &Value) -> Result<u64, String> {
+    let version = match config.get("version") {
+        Some(version) => version,
+        None => return Ok(1)
+    };
+
+    match version.as_u64() {
+        Some(version) if (1..=CONFIG_VERSION).contains(&version) => Ok(version),
+        Some(version) if version > CONFIG_VERSION => {
+            Err(format!("Config version {} is newer than {}, the latest version this x-ray reads", version, CONFIG_VERSION))
+        },
+        _ => Err(format!("Invalid config version {}, expected a number from 1 to {}", version, CONFIG_VERSION))
+    }
+}
+
+/// Upgrade a config of an older version to the current version.
+pub fn upgrade(mut config: Value, version: u64) -> Value {
+    upgrade_packages(&mut config["root"], version);
+
+    config["version"] = json!(CONFIG_VERSION);
+    config
+}
+
+/// Same as `upgrade`, for a file included by a package.
+pub fn upgrade_package_file(mut package_file: Value, version: u64) -> Value {
+    upgrade_packages(&mut package_file, version);
+
+    package_file["version"] = json!(CONFIG_VERSION);
+    package_file
+}
+
+/// Upgrade the packages and modules of the root or a package file, one
+/// version at a time.
+fn upgrade_packages(root: &mut Value, version: u64) {
+    if version < 2 {
+        upgrade_v1(root);
+    }
+}
+
+/// Version 1 parameters are python source, e.g. `"timeout=30"`.
+/// Version 2 has tables with the name, annotation, default and kind.
+fn upgrade_v1(root: &mut Value) {
+    upgrade_package(root, &|function| {
+        if let Some(parameters) = function.get_mut("parameters").and_then(|x| x.as_array_mut()) {
+            for parameter in parameters.iter_mut() {
+                let table = match *parameter {
+                    Value::String(ref source) => serde_json::to_value(Parameter::from_source(source)).unwrap(),
+                    _ => continue
+                };
+                *parameter = table;
+            }
+        }
+    });
+}
+
+/// Call `upgrade_function` on every function and method of the root or
+/// package, and of the packages in it.
+fn upgrade_package<F>(package: &mut Value, upgrade_function: &F) where F: Fn(&mut Value) {
+    for package in items(package, "packages") {
+        upgrade_package(package, upgrade_function);
+    }
+
+    for module in items(package, "modules") {
+        for function in items(module, "functions") {
+            upgrade_function(function);
+        }
+        upgrade_classes(module, upgrade_function);
+    }
+}
+
+fn upgrade_classes<F>(parent: &mut Value, upgrade_function: &F) where F: Fn(&mut Value) {
+    for class in items(parent, "classes") {
+        for method in items(class, "methods") {
+            upgrade_function(method);
+        }
+        upgrade_classes(class, upgrade_function);
+    }
+}
+
+/// The items of an array in a table, none if it's not an array.
+fn items<'a>(table: &'a mut Value, key: &str) -> Vec<&'a mut Value> {
+    match table.get_mut(key).and_then(|x| x.as_array_mut()) {
+        Some(items) => items.iter_mut().collect(),
+        None => Vec::new()
+    }
+}
+
+#[test]
+fn test_migrate_version() {
+    assert_eq!(version(&json!({"root": {}})), Ok(1));
+    assert_eq!(version(&json!({"version": 2, "root": {}})), Ok(2));
+    assert!(version(&json!({"version": 3, "root": {}})).is_err());
+    assert!(version(&json!({"version": 0, "root": {}})).is_err());
+    assert!(version(&json!({"version": "2", "root": {}})).is_err());
+}
+
+#[test]
+fn test_migrate_upgrade_v1() {
+    let config = json!({
+        "root": {
+            "name": "project",
+            "packages": [{
+                "name": "api",
+                "modules": [{
+                    "name": "api",
+                    "functions": [{"name": "create_api", "parameters": ["id", "new=False", "*args"]}],
+                    "classes": [{
+                        "name": "Api",
+                        "methods": [{"name": "get", "parameters": ["self"]}],
+                        "classes": [{"name": "Meta", "methods": [{"name": "load", "parameters": ["**kwargs"]}]}]
+                    }]
+                }]
+            }]
+        },
+        "validation": {"parameters": "^[a-z]+$"}
+    });
+
+    let module = json!({
+        "name": "api",
+        "functions": [{"name": "create_api", "parameters": [
+            {"name": "id"},
+            {"name": "new", "default": "False"},
+            {"name": "args", "kind": "var_positional"}
+        ]}],
+        "classes": [{
+            "name": "Api",
+            "methods": [{"name": "get", "parameters": [{"name": "self"}]}],
+            "classes": [{"name": "Meta", "methods": [{"name": "load", "parameters": [{"name": "kwargs", "kind": "var_keyword"}]}]}]
+        }]
+    });
+
+    assert_eq!(upgrade(config, 1), json!({
+        "version": 2,
+        "root": {"name": "project", "packages": [{"name": "api", "modules": [module]}]},
+        "validation": {"parameters": "^[a-z]+$"}
+    }));
+}
@@ -1,146 +1,392 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use util;
+use cli::Order;
 use parser;
 use parser::{ItemKind};
-use structures::{Root, Package, Module, Class, Function};
+use error::{Error, Result};
+use structures::{Root, Package, Module, Class, Function, Import, Variable, Attribute};
 
-/// Check if a given directory is a python package.
-fn is_package(dir_path: &PathBuf) -> bool {
-    let dirs = fs::read_dir(dir_path).unwrap();
+/// Read the entries of a directory, sorted by file name so that the
+/// result doesn't depend on the order of the file system.
+fn read_dir(dir_path: &PathBuf) -> Result<Vec<fs::DirEntry>> {
+    let dirs = match fs::read_dir(dir_path) {
+        Ok(dirs) => dirs,
+        Err(e) => return Err(Error::io(dir_path.clone(), e))
+    };
+
+    let mut entries = Vec::new();
     for dir in dirs {
-        let dir_entry = dir.unwrap();
+        match dir {
+            Ok(dir_entry) => entries.push(dir_entry),
+            Err(e) => return Err(Error::io(dir_path.clone(), e))
+        }
+    }
+
+    entries.sort_by_key(|x| x.file_name());
 
+    Ok(entries)
+}
+
+/// Check if a given entry is a directory.
+fn is_dir(dir_entry: &fs::DirEntry) -> Result<bool> {
+    match dir_entry.metadata() {
+        Ok(metadata) => Ok(metadata.is_dir()),
+        Err(e) => Err(Error::io(dir_entry.path(), e))
+    }
+}
+
+/// Check if a given directory is a python package.
+fn is_package(dir_path: &PathBuf) -> Result<bool> {
+    let dirs = read_dir(dir_path)?;
+    for dir_entry in dirs {
         let file_name = dir_entry.file_name();
-        let file_name = file_name.to_str().unwrap();
-        if file_name == "__init__.py" {
-            return true;
+        // Stub only packages, like the ones in typeshed, have an __init__.pyi.
+        if file_name == "__init__.py" || file_name == "__init__.pyi" {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// The name of the module in a python source or stub file, and whether
+/// the file is a stub. Returns None for other files, for `__init__` and
+/// for names that are not valid UTF-8.
+fn module_name(path: &Path) -> Option<(&str, bool)> {
+    let is_stub = match path.extension().and_then(|x| x.to_str()) {
+        Some("py") => false,
+        Some("pyi") => true,
+        _ => return None
+    };
+
+    match path.file_stem().and_then(|x| x.to_str()) {
+        Some("__init__") | None => None,
+        Some(name) => Some((name, is_stub))
+    }
+}
+
+/// Merge the stubs into the modules with the same name, stubs without a
+/// source file are modules of their own.
+fn merge_stubs(mut modules: Vec<Module>, stubs: Vec<Module>, order: Order) -> Vec<Module> {
+    for stub in stubs {
+        match modules.iter().position(|x| x.name == stub.name) {
+            Some(index) => {
+                merge_stub(&mut modules[index], stub);
+                sort_module(&mut modules[index], order);
+            },
+            None => modules.push(stub)
+        }
+    }
+
+    modules.sort_by(|a, b| a.name.cmp(&b.name));
+    modules
+}
+
+fn is_overload(function: &Function) -> bool {
+    function.decorators.iter().any(|x| x == "overload" || x == "typing.overload")
+}
+
+/// Take the annotations of the stub function, the rest comes from the
+/// source. Default values of stubs are `...`, so they are not used.
+fn merge_function_types(function: &mut Function, stub: &Function) {
+    for parameter in &mut function.parameters {
+        let annotation = stub.parameters.iter()
+            .find(|x| x.name == parameter.name)
+            .and_then(|x| x.annotation.clone());
+
+        if annotation.is_some() {
+            parameter.annotation = annotation;
+        }
+    }
+
+    if stub.returns.is_some() {
+        function.returns = stub.returns.clone();
+    }
+
+    if function.description.is_none() {
+        function.description = stub.description.clone();
+    }
+}
+
+/// Merge the functions of a stub into the functions of the source.
+/// The overloads of a stub replace the overloads in the source and go
+/// before the implementation.
+fn merge_functions(functions: Vec<Function>, stubs: Vec<Function>) -> Vec<Function> {
+    let source_names = functions.iter().map(|x| x.name.clone()).collect::<Vec<_>>();
+    let mut result: Vec<Function> = Vec::new();
+
+    for mut function in functions {
+        let overloads = stubs.iter()
+            .filter(|x| x.name == function.name && is_overload(x))
+            .collect::<Vec<_>>();
+
+        if !overloads.is_empty() {
+            if !result.iter().any(|x| x.name == function.name) {
+                result.extend(overloads.into_iter().cloned());
+            }
+
+            if is_overload(&function) {
+                continue;
+            }
+        } else if let Some(stub) = stubs.iter().find(|x| x.name == function.name) {
+            merge_function_types(&mut function, stub);
+        }
+
+        result.push(function);
+    }
+
+    result.extend(stubs.into_iter().filter(|x| !source_names.contains(&x.name)));
+    result
+}
+
+/// Take the annotations of the attributes in the stub, attributes that are
+/// only in the stub are added.
+fn merge_attributes(attributes: &mut Vec<Attribute>, stubs: Vec<Attribute>) {
+    for stub in stubs {
+        match attributes.iter_mut().find(|x| x.name == stub.name) {
+            Some(attribute) => {
+                if stub.annotation.is_some() {
+                    attribute.annotation = stub.annotation;
+                }
+            },
+            None => attributes.push(stub)
         }
     }
+}
+
+fn merge_classes(classes: &mut Vec<Class>, stubs: Vec<Class>) {
+    for stub in stubs {
+        let index = match classes.iter().position(|x| x.name == stub.name) {
+            Some(index) => index,
+            None => {
+                classes.push(stub);
+                continue;
+            }
+        };
+
+        let class = &mut classes[index];
+        if class.parents.is_empty() {
+            class.parents = stub.parents;
+        }
+
+        if class.description.is_none() {
+            class.description = stub.description;
+        }
+
+        merge_attributes(&mut class.attributes, stub.attributes);
 
-    false
+        let methods = class.methods.drain(..).collect();
+        class.methods = merge_functions(methods, stub.methods);
+
+        merge_classes(&mut class.classes, stub.classes);
+    }
+}
+
+/// Merge the stub of a module into the module parsed from its source.
+/// The stub takes precedence for the types, everything else comes from the
+/// source. Items that are only declared in the stub are added.
+fn merge_stub(module: &mut Module, stub: Module) {
+    if module.description.is_none() {
+        module.description = stub.description;
+    }
+
+    // Annotations from the stub may need names that are only imported there.
+    for import in stub.imports {
+        if !module.imports.contains(&import) {
+            module.imports.push(import);
+        }
+    }
+
+    if module.exports.is_empty() {
+        module.exports = stub.exports;
+    }
+
+    for stub_variable in stub.variables {
+        match module.variables.iter_mut().find(|x| x.name == stub_variable.name) {
+            Some(variable) => {
+                if stub_variable.annotation.is_some() {
+                    variable.annotation = stub_variable.annotation;
+                }
+            },
+            None => module.variables.push(stub_variable)
+        }
+    }
+
+    let functions = module.functions.drain(..).collect();
+    module.functions = merge_functions(functions, stub.functions);
+
+    merge_classes(&mut module.classes, stub.classes);
 }
 
 /// Parse the package and the modules it has.
 /// Do this recursively.
-fn parse_package(dir_path: &PathBuf) -> Package {
+fn parse_package(dir_path: &PathBuf, order: Order) -> Result<Package> {
     let package_name = match dir_path.file_name() {
         Some(x) => x.to_str().unwrap_or("").to_string(),
         None => "".to_string()
     };
 
-    let dirs = fs::read_dir(dir_path).unwrap();
+    let dirs = read_dir(dir_path)?;
     let mut pac_modules: Vec<Module> = Vec::new();
+    let mut pac_stubs: Vec<Module> = Vec::new();
     let mut nested_packages: Vec<Package> = Vec::new();
 
-    for dir in dirs {
-        let dir_entry = dir.unwrap();
+    for dir_entry in dirs {
         let dir_path = dir_entry.path();
-        let file_name = dir_entry.file_name();
-        let mut file_name = file_name.to_str().unwrap();
 
-        let is_dir: bool = dir_entry.metadata().unwrap().is_dir();
+        if is_dir(&dir_entry)? == false {
 
-        if is_dir == false {
-
-            if file_name.ends_with(".py") && file_name != "__init__.py" {
-                file_name = file_name.split(".").collect::<Vec<_>>()[0];
-                pac_modules.push(parse_module(&dir_path, file_name));
+            if let Some((module_name, is_stub)) = module_name(&dir_path) {
+                let module = parse_module(&dir_path, module_name, order)?;
+                if is_stub {
+                    pac_stubs.push(module);
+                } else {
+                    pac_modules.push(module);
+                }
             }
         } else {
-            let is_py_package = is_package(&dir_path);
+            let is_py_package = is_package(&dir_path)?;
             if is_py_package == true {
-                let package_res = parse_package(&dir_path);
+                let package_res = parse_package(&dir_path, order)?;
                 nested_packages.push(package_res);
             }
         }
     }
 
-    Package {
+    Ok(Package {
         name: package_name,
+        include: Vec::new(),
         packages: nested_packages,
-        modules: pac_modules
+        modules: merge_stubs(pac_modules, pac_stubs, order)
+    })
+}
+
+/// Sort classes by name, along with their methods and nested classes.
+fn sort_classes(classes: &mut Vec<Class>) {
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for class in classes {
+        class.methods.sort_by(|a, b| a.name.cmp(&b.name));
+        sort_classes(&mut class.classes);
     }
 }
 
-fn parse_module(file_path: &PathBuf, file_name: &str) -> Module {
-    let module_src = util::read_file(file_path.to_str().unwrap());
-    let module_src = module_src.trim();
-    let src_bytes = module_src.as_bytes();
-    println!("Parsing file {:?}", file_path);
+/// Sort the functions and classes of a module by name, unless the order
+/// of declaration is kept. Variables keep their order, a value can depend
+/// on the ones before it.
+fn sort_module(module: &mut Module, order: Order) {
+    if let Order::Name = order {
+        module.functions.sort_by(|a, b| a.name.cmp(&b.name));
+        sort_classes(&mut module.classes);
+    }
+}
+
+fn parse_module(file_path: &PathBuf, file_name: &str, order: Order) -> Result<Module> {
+    let module_src = util::read_file(file_path)?;
+    if let Err(line) = parser::check_indentation(module_src.as_bytes()) {
+        return Err(Error::Indentation {file: file_path.clone(), line: line});
+    }
 
+    // The parser expects every line, including the last, to end with a newline.
+    // Offsets of errors are in the original source, with the trimmed bytes.
+    let leading = module_src.len() - module_src.trim_left().len();
+    let module_src = module_src.trim().to_string() + "\n";
+    let src_bytes = module_src.as_bytes();
 
-    let parsing_result = parser::parse(src_bytes);
+    let parsing_result = match parser::parse(src_bytes) {
+        Ok(result) => result,
+        Err(offset) => return Err(Error::Parse {file: file_path.clone(), offset: leading + offset})
+    };
     let mut func_vec: Vec<Function> = Vec::new();
     let mut class_vec: Vec<Class> = Vec::new();
+    let mut variables: Vec<Variable> = Vec::new();
+    let mut exports: Vec<String> = Vec::new();
+    let mut imports: Vec<Import> = Vec::new();
 
     let mut module_description = None;
     for res in parsing_result {
         match res.node {
-            ItemKind::Function{name, description: desc, parameters: params} => {
+            ItemKind::Function{name, description: desc, parameters: params, returns, is_async, decorators} => {
                 func_vec.push(Function {
                     name: name,
                     description: desc,
-                    parameters: params
+                    parameters: params,
+                    returns: returns,
+                    is_async: is_async,
+                    decorators: decorators
                 });
             },
-            ItemKind::Class{name, description: desc, parents, methods: mthds} => {
+            ItemKind::Class{name, description: desc, parents, decorators, attributes, methods: mthds, classes} => {
                 class_vec.push(Class {
                     name: name,
                     description: desc,
                     parents: parents,
-                    methods: mthds
+                    decorators: decorators,
+                    attributes: attributes,
+                    methods: mthds,
+                    classes: classes
                 });
             },
             ItemKind::Module{description:desc} => {
                 module_description = desc;
             },
-            _ => println!("Found other type in parsing result")
+            ItemKind::Import{imports: names} => imports.extend(names),
+            ItemKind::Variable{variable} => variables.push(variable),
+            ItemKind::Exports{names} => exports = names,
+            _ => {}
         }
     }
-    let module_res = Module {
+
+    let mut module_res = Module {
         name: file_name.to_string(),
         description: module_description,
+        imports: imports,
+        exports: exports,
+        variables: variables,
         functions: func_vec,
         classes: class_vec
     };
 
-    module_res
+    sort_module(&mut module_res, order);
+
+    Ok(module_res)
 }
 
-pub fn parse(parse_dir: &str) -> Root {
+pub fn parse(parse_dir: &str, order: Order) -> Result<Root> {
     let root_name = parse_dir.clone().to_string();
     let dir_path = PathBuf::from(parse_dir);
-    let dirs = fs::read_dir(dir_path).unwrap();
+    let dirs = read_dir(&dir_path)?;
     let mut root_packages: Vec<Package> = Vec::new();
     let mut root_modules: Vec<Module> = Vec::new();
+    let mut root_stubs: Vec<Module> = Vec::new();
 
-    for dir in dirs {
-        let dir_entry = dir.unwrap();
+    for dir_entry in dirs {
         let dir_path = dir_entry.path();
-        let is_dir: bool = dir_entry.metadata().unwrap().is_dir();
-
-        if is_dir == false {
-            let file_name = dir_entry.file_name();
-            let mut file_name = file_name.to_str().unwrap();
-            if file_name.ends_with(".py") && file_name != "__init__.py" {
-                file_name = file_name.split(".").collect::<Vec<_>>()[0];
-                root_modules.push(parse_module(&dir_path, file_name));
+
+        if is_dir(&dir_entry)? == false {
+            if let Some((module_name, is_stub)) = module_name(&dir_path) {
+                let module = parse_module(&dir_path, module_name, order)?;
+                if is_stub {
+                    root_stubs.push(module);
+                } else {
+                    root_modules.push(module);
+                }
             }
-        } else {        
-            let is_py_package = is_package(&dir_path);
+        } else {
+            let is_py_package = is_package(&dir_path)?;
             if is_py_package == true {
-                let package_res = parse_package(&dir_path);
+                let package_res = parse_package(&dir_path, order)?;
                 root_packages.push(package_res);
             }
-        } 
+        }
     }
     let root_res = Root {
         name: root_name,
         packages: root_packages,
-        modules: root_modules
+        modules: merge_stubs(root_modules, root_stubs, order)
     };
 
-    root_res
+    Ok(root_res)
 }
@@ -8,13 +8,16 @@ macro_rules! block (
             use nom::InputIter;
             use nom::Slice;
             use nom::AsChar;
+            use $crate::parser::util::TAB_SIZE;
             let input = $i;
 
             let cnt = $len as usize;
             let mut res: nom::IResult<_,_> = nom::IResult::Incomplete(nom::Needed::Size(cnt));
 
             let mut start = false;
+            // indentation width in columns and in bytes.
             let mut indent = 0;
+            let mut indent_len = 0;
             for (idx, item) in input.iter_indices() {
                 res = nom::IResult::Done(input.slice(idx + 1..), input.slice(0..idx + 1));
 
@@ -23,15 +26,24 @@ macro_rules! block (
                     start = true;
                 } else if start == true && item.as_char() == ' ' {
                     indent += 1;
+                    indent_len += 1;
+                } else if start == true && item.as_char() == '\t' {
+                    // A tab advances to the next multiple of the tab size.
+                    indent = (indent / TAB_SIZE + 1) * TAB_SIZE;
+                    indent_len += 1;
+                } else if start == true && item.as_char() == '\r' {
+                    indent_len += 1;
                 } else if start == true && item.as_char() == '\n' {
                     indent = 0;
-                } else if start == true && item.as_char() != ' ' {
+                    indent_len = 0;
+                } else if start == true {
                     start = false;
                     if indent <= cnt {
-                        res = nom::IResult::Done(input.slice(idx - indent..), input.slice(0..idx - indent));
+                        res = nom::IResult::Done(input.slice(idx - indent_len..), input.slice(0..idx - indent_len));
                         break;
                     } else {
                         indent = 0;
+                        indent_len = 0;
                     }
                 }
             };
@@ -50,6 +62,7 @@ macro_rules! many0_block(
       use nom::InputLength;
       use nom::InputIter;
       use nom::AsChar;
+      use $crate::parser::util::TAB_SIZE;
 
       let ret;
       let mut res   = ::std::vec::Vec::new();
@@ -64,13 +77,14 @@ macro_rules! many0_block(
         let cnt = $len as usize;
         let mut indent = 0;
 
-        for (idx, item) in input.iter_indices() {
-            if item.as_char() == '\n' || item.as_char() == ' ' {
-                if item.as_char() == ' ' {
-                    indent += 1;
-                }
-            } else {
-                break;
+        // The indentation of the next line that is not empty.
+        for item in input.iter_elements() {
+            match item.as_char() {
+                ' ' => indent += 1,
+                // A tab advances to the next multiple of the tab size.
+                '\t' => indent = (indent / TAB_SIZE + 1) * TAB_SIZE,
+                '\r' | '\n' => indent = 0,
+                _ => break
             }
         };
 
@@ -114,41 +128,6 @@ macro_rules! many0_block(
   );
 );
 
-/// Take until a line containing a given tag.
-/// starting from \n to the next \n see if the content has the tag.
-/// If it has then consume until the previous line. Else consume the line.
-#[macro_export]
-macro_rules! take_until_line_containing_tag (
-  ($i:expr, $substr:expr) => (
-    {
-      use nom::InputLength;
-      use nom::FindSubstring;
-      use nom::Slice;
-
-      let res: nom::IResult<_,_> = if $substr.input_len() > $i.input_len() {
-        nom::IResult::Incomplete(nom::Needed::Size($substr.input_len()))
-      } else {
-        match ($i).find_substring($substr) {
-          None => {
-            nom::IResult::Error(error_position!(nom::ErrorKind::TakeUntil,$i))
-          },
-          Some(index) => {
-            let mut ind = index;
-            while ind != 0 {
-                if $i.slice(ind..ind + 1)[0] == 10 {
-                    break;
-                }
-                ind -= 1;
-            }
-            nom::IResult::Done($i.slice(ind..), $i.slice(0..ind))
-          },
-        }
-      };
-      res
-    }
-  );
-);
-
 /// Returns false if there is no data remaining. Else returns true.
 #[macro_export]
 macro_rules! has_data (
@@ -6,7 +6,7 @@ use std;
 
 use nom;
 
-use structures::{Function};
+use structures::{Attribute, AttributeOrigin, Class, Function, Import, Parameter, ParameterKind, Variable, is_balanced};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Item {
@@ -15,16 +15,19 @@ pub struct Item {
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum ItemKind {
-    Import {path: String},
-    ImportFrom {module: String, name: String, level: i32},
+    Import {imports: Vec<Import>},
     Shebang {path: String},
     Module {description: Option<String>},
-    Class {name: String, description: Option<String>, parents: Vec<String>, methods: Vec<Function>},
-    Function {name: String, description: Option<String>, parameters: Vec<String>},
+    Class {name: String, description: Option<String>, parents: Vec<String>, decorators: Vec<String>,
+           attributes: Vec<Attribute>, methods: Vec<Function>, classes: Vec<Class>},
+    Variable {variable: Variable},
+    Exports {names: Vec<String>},
+    Function {name: String, description: Option<String>, parameters: Vec<Parameter>, returns: Option<String>, is_async: bool,
+              decorators: Vec<String>},
     Code {code: String}
 }
 
-named!(items<Vec<Item>>, many0!(alt!(
+named!(item<Item>, alt!(
     shebang
     |
     item_import
@@ -35,91 +38,175 @@ named!(items<Vec<Item>>, many0!(alt!(
     |
     item_fn
     |
+    item_variable
+    |
     item_code
-)));
+));
+
+#[cfg(test)]
+fn items(input: &[u8]) -> nom::IResult<&[u8], Vec<Item>> {
+    many0!(input, item)
+}
 
 named!(item_code<Item>, do_parse!(
-    many0!(nom::newline) >>
+    many0!(nom::line_ending) >>
     code: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
-    many0!(nom::newline) >>
+    many0!(nom::line_ending) >>
     (Item {
         node: ItemKind::Code {
-            code: code.to_string()
+            code: code.trim_right_matches('\r').to_string()
         }
     })
 ));
 
+/// A module level assignment, e.g. `MAX_RETRIES: int = 3`.
+/// The value may continue on the following lines while a bracket is open.
+/// An assignment to `__all__` gives the exports of the module.
+fn item_variable(input: &[u8]) -> nom::IResult<&[u8], Item> {
+    let start = input.iter().take_while(|x| **x == b'\n' || **x == b'\r').count();
+
+    // Only plain names at the start of the line, followed by : or =.
+    let name_len = match util::ident(&input[start..]) {
+        nom::IResult::Done(_, name) => name.len(),
+        _ => 0
+    };
+    let after_name = input[start + name_len..].iter().find(|x| **x != b' ' && **x != b'\t');
+    if name_len == 0 || !(after_name == Some(&b'=') || after_name == Some(&b':')) {
+        return nom::IResult::Error(error_position!(nom::ErrorKind::Custom(0), input));
+    }
+
+    let mut end = start;
+    let source = loop {
+        match input[end..].iter().position(|x| *x == b'\n') {
+            Some(index) => end += index + 1,
+            None => return nom::IResult::Error(error_position!(nom::ErrorKind::Custom(0), input))
+        }
+
+        let source = match std::str::from_utf8(&input[start..end]) {
+            Ok(source) => source,
+            Err(_) => return nom::IResult::Error(error_position!(nom::ErrorKind::Custom(0), input))
+        };
+
+        if is_balanced(source) {
+            break source;
+        }
+    };
+
+    let variable = match Variable::from_source(source) {
+        Some(variable) => variable,
+        None => return nom::IResult::Error(error_position!(nom::ErrorKind::Custom(0), input))
+    };
+
+    let remaining = &input[end..];
+    let remaining = &remaining[remaining.iter().take_while(|x| **x == b'\n' || **x == b'\r').count()..];
+
+    let node = if variable.name == "__all__" {
+        ItemKind::Exports {names: string_literals(variable.value.as_ref().map_or("", |x| x))}
+    } else {
+        ItemKind::Variable {variable: variable}
+    };
+
+    nom::IResult::Done(remaining, Item {node: node})
+}
+
+/// The contents of the string literals in a python expression,
+/// e.g. the names in `["load", 'dump']`.
+fn string_literals(source: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    let mut current: Option<(char, String)> = None;
+
+    for chr in source.chars() {
+        current = match current {
+            Some((quote, literal)) => {
+                if chr == quote {
+                    literals.push(literal);
+                    None
+                } else {
+                    Some((quote, literal + &chr.to_string()))
+                }
+            },
+            None if chr == '"' || chr == '\'' => Some((chr, String::new())),
+            None => None
+        };
+    }
+
+    literals
+}
+
 named!(shebang<Item>, do_parse!(
-    many0!(nom::newline) >>
+    many0!(nom::line_ending) >>
     tag!("#!") >>
     many1!(nom::space) >>
     path: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
     (Item {
         node: ItemKind::Shebang {
-            path: path.to_string()
+            path: path.trim_right_matches('\r').to_string()
         }
     })
 ));
 
 named!(item_import<Item>, do_parse!(
-    many0!(nom::newline) >>
+    many0!(nom::line_ending) >>
     tag!("import") >>
     many1!(nom::space) >>
-    path: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
+    names: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
     (Item {
         node: ItemKind::Import {
-            path: path.to_string()
+            imports: import_names(names).into_iter().map(|(name, alias)| Import {
+                module: None,
+                name: name,
+                alias: alias,
+                level: 0
+            }).collect()
         }
     })
 ));
 
 named!(item_import_from<Item>, do_parse!(
-    many0!(nom::newline) >>
+    many0!(nom::line_ending) >>
     tag!("from") >>
     many1!(nom::space) >>
     module: map_res!(take_until_and_consume!(" import"), std::str::from_utf8) >>
     many1!(nom::space) >>
-    name: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
+    names: map_res!(alt!(
+        // Parenthesized names can span several lines.
+        terminated!(delimited!(tag!("("), take_until!(")"), tag!(")")), take_until_and_consume!("\n"))
+        |
+        take_until_and_consume!("\n")
+    ), std::str::from_utf8) >>
     (Item {
-        node: ItemKind::ImportFrom {
-            module: {
-                // Just strip the initial dots in the module.
-                let mut module_vec = Vec::new();
-                let mut init = false;
-                for ch in module.chars() {
-                    if init == false && ch == '.' {
-                        continue
-                    } else {
-                        init = true;
-                        module_vec.push(ch);
-                    }
-                }
-                let module_str: String = module_vec.into_iter().collect();
-                module_str
-            },
-            name: name.to_string(),
-            level: {
-                let mut level;
+        node: ItemKind::Import {
+            imports: {
+                let module = module.trim();
                 // Level: 0 is absolute import
-                if module.starts_with(".") {
-                    level = 0;
-                    for ch in module.chars() {
-                        if ch == '.' {
-                            level += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                } else {
-                    level = 0;
-                }
-
-                level
+                let level = module.chars().take_while(|x| *x == '.').count();
+
+                import_names(names).into_iter().map(|(name, alias)| Import {
+                    module: Some(module[level..].to_string()),
+                    name: name,
+                    alias: alias,
+                    level: level
+                }).collect()
             }
         }
     })
 ));
 
+/// Split the names of an import statement, e.g. `path, sep as separator`,
+/// into names and aliases. Comments and a trailing comma are allowed.
+fn import_names(names: &str) -> Vec<(String, Option<String>)> {
+    names.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split(','))
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| match name.find(" as ") {
+            Some(index) => (name[..index].trim().to_string(), Some(name[index + 4..].trim().to_string())),
+            None => (name.to_string(), None)
+        })
+        .collect()
+}
+
 named!(item_module_doc_string<Item>, do_parse!(
     description: opt!(doc_string) >>
     (Item {
@@ -136,38 +223,58 @@ named!(item_module_doc_string<Item>, do_parse!(
 ///    def __init__(self):
 ///        pass
 ///
-named!(parent<Vec<&[u8]>>, do_parse!(
+/// The parents are split like parameters, so `models.Model`, `Generic[T]`
+/// and `metaclass=ABCMeta` are kept whole.
+named!(parent<Vec<String>>, do_parse!(
     tag!("(") >>
-    parents: ws!(separated_list!(tag!(","), util::ident)) >>
+    parents: call!(util::func_params) >>
     tag!(")") >>
     (parents)
 ));
 
 pub enum ClassBlock {
-    Method (Item),
+    Method (Item, String),
+    Class (Item),
     Code (String)
 }
 
 named!(item_class_code<ClassBlock>, do_parse!(
-    many0!(nom::newline) >>
+    many0!(nom::line_ending) >>
     code: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
-    (ClassBlock::Code(code.to_string()))
+    (ClassBlock::Code(code.trim_right_matches('\r').to_string()))
 ));
 
-named!(item_class_method<ClassBlock>, do_parse!(
-    method: call!(item_fn) >>
-    (ClassBlock::Method(method))
+/// A method along with its source, which is needed to find the
+/// instance attributes assigned in `__init__`.
+fn item_class_method(input: &[u8]) -> nom::IResult<&[u8], ClassBlock> {
+    match item_fn(input) {
+        nom::IResult::Done(remaining, method) => {
+            let source = String::from_utf8_lossy(&input[..input.len() - remaining.len()]).into_owned();
+            nom::IResult::Done(remaining, ClassBlock::Method(method, source))
+        },
+        nom::IResult::Error(e) => nom::IResult::Error(e),
+        nom::IResult::Incomplete(needed) => nom::IResult::Incomplete(needed)
+    }
+}
+
+// A class nested in a class, e.g. `class Meta:` in a django model.
+named!(item_class_nested<ClassBlock>, do_parse!(
+    class: call!(item_class) >>
+    (ClassBlock::Class(class))
 ));
 
 named!(item_class_block<ClassBlock>, alt!(
     item_class_method
     |
+    item_class_nested
+    |
     item_class_code
 ));
 
 named!(item_class<Item>, do_parse!(
-    many0!(nom::newline) >>
-    start_len: many0!(tag!(" ")) >>
+    many0!(nom::line_ending) >>
+    start_len: call!(util::indentation) >>
+    decorators: many0!(decorator) >>
     tag!("class") >>
     many1!(nom::space) >>
     name: map_res!(util::ident, std::str::from_utf8) >>
@@ -175,81 +282,188 @@ named!(item_class<Item>, do_parse!(
     tag!(":") >>
     description: opt!(doc_string) >>
     opt!(util::emptyline) >>
-    opt!(take_until_line_containing_tag!("def")) >>
-    class_items: many0_block!(start_len.len(), call!(item_class_block)) >>
-    many0!(nom::newline) >>
+    class_items: many0_block!(start_len, call!(item_class_block)) >>
+    many0!(nom::line_ending) >>
     (Item {
-        node: ItemKind::Class {
-            name: name.to_string(),
-            description: description,
-            parents: {
-                let parents = match parents {
-                    Some(x) => x,
-                    None => Vec::new()
-                };
+        node: {
+            let attributes = class_attributes(&class_items);
+            let (methods, classes) = class_members(class_items);
+
+            ItemKind::Class {
+                name: name.to_string(),
+                description: description,
+                parents: parents.unwrap_or_default(),
+                decorators: decorators,
+                attributes: attributes,
+                methods: methods,
+                classes: classes
+            }
+        }
+    })
+));
 
-                // Return parents after converting to String.
-                parents.iter().map(|x| std::str::from_utf8(x).unwrap().to_string()).collect::<Vec<_>>()
+/// Split the methods and the nested classes out of the blocks of a class.
+fn class_members(class_items: Vec<ClassBlock>) -> (Vec<Function>, Vec<Class>) {
+    let mut methods = Vec::new();
+    let mut classes = Vec::new();
+
+    for class_item in class_items {
+        match class_item {
+            ClassBlock::Method(item, _) => {
+                if let ItemKind::Function {name, description, parameters, returns, is_async, decorators} = item.node {
+                    methods.push(Function {
+                        name: name,
+                        description: description,
+                        parameters: parameters,
+                        returns: returns,
+                        is_async: is_async,
+                        decorators: decorators
+                    });
+                }
             },
-            methods: {
-                let mut result = Vec::new();
-                let mut methods = Vec::new();
-                for class_item in class_items {
-                    match class_item {
-                        ClassBlock::Method(func) => methods.push(func),
-                        ClassBlock::Code(_) => {}
-                    };
+            ClassBlock::Class(item) => {
+                if let ItemKind::Class {name, description, parents, decorators, attributes, methods: class_methods,
+                                        classes: nested_classes} = item.node {
+                    classes.push(Class {
+                        name: name,
+                        description: description,
+                        parents: parents,
+                        decorators: decorators,
+                        attributes: attributes,
+                        methods: class_methods,
+                        classes: nested_classes
+                    });
                 }
-                for item in methods {
-                    match item.node {
-                        ItemKind::Function {name, description, parameters} => {
-                            result.push(Function {
-                                name: name,
-                                description: description,
-                                parameters: parameters
-                            });
-                        },
-                        _ => {}
-                    }
+            },
+            ClassBlock::Code(_) => {}
+        }
+    }
+
+    (methods, classes)
+}
+
+/// Find the attributes of a class. Class attributes are assignments at the
+/// indentation of the class body, instance attributes are assignments to
+/// `self` in `__init__`. An attribute defined in both places is kept once,
+/// as a class attribute.
+fn class_attributes(class_items: &[ClassBlock]) -> Vec<Attribute> {
+    let indent = |line: &str| line.len() - line.trim_left().len();
+
+    let body_indent = class_items.iter().filter_map(|x| match *x {
+        ClassBlock::Code(ref line) if !line.trim().is_empty() => Some(indent(line)),
+        _ => None
+    }).min();
+
+    let mut attributes: Vec<Attribute> = Vec::new();
+    let mut instance_attributes = Vec::new();
+
+    for class_item in class_items {
+        match *class_item {
+            ClassBlock::Code(ref line) => {
+                if Some(indent(line)) == body_indent {
+                    attributes.extend(Attribute::from_source(line, AttributeOrigin::Class));
                 }
+            },
+            ClassBlock::Method(Item {node: ItemKind::Function {ref name, ..}}, ref source) if name == "__init__" => {
+                for line in source.lines().map(|x| x.trim()).filter(|x| x.starts_with("self.")) {
+                    instance_attributes.extend(Attribute::from_source(&line[5..], AttributeOrigin::Instance));
+                }
+            },
+            _ => {}
+        }
+    }
 
-                result
-            }
+    for attribute in instance_attributes {
+        if !attributes.iter().any(|x| x.name == attribute.name) {
+            attributes.push(attribute);
         }
-    })
-));
+    }
+
+    attributes
+}
 
 named!(item_fn<Item>, do_parse!(
-    many0!(nom::newline) >>
-    start_len: many0!(tag!(" ")) >>
+    many0!(nom::line_ending) >>
+    start_len: call!(util::indentation) >>
     decorators: many0!(decorator) >>
+    is_async: opt!(terminated!(tag!("async"), many1!(nom::space))) >>
     tag!("def") >>
     space: many1!(nom::space) >>
     name: map_res!(util::ident, std::str::from_utf8) >>
     ws!(tag!("(")) >>
-    params: ws!(separated_list!(tag!(","), util::func_param)) >>
-    opt!(tag!(",")) >>
-    ws!(tag!("):")) >>
-    opt!(nom::newline) >>
+    params: call!(util::func_params) >>
+    ws!(tag!(")")) >>
+    returns: opt!(return_annotation) >>
+    ws!(tag!(":")) >>
+    opt!(nom::line_ending) >>
     description: opt!(doc_string) >>
     has_data: has_data!() >>
-    cond!(has_data, block!(start_len.len())) >>
+    cond!(has_data, block!(start_len)) >>
 
     (Item {
         node: ItemKind::Function {
             name: name.to_string(),
             description: description,
-            parameters: params.iter().map(|x| std::str::from_utf8(x).unwrap().to_string()).collect::<Vec<_>>()
+            parameters: parameters(params),
+            returns: returns,
+            is_async: is_async.is_some(),
+            decorators: decorators
         }
     })
 ));
 
+// Return annotation of a function.
+// Example:
+//
+// def hello() -> Dict[str, int]:
+//
+named!(return_annotation<String>, do_parse!(
+    tag!("->") >>
+    returns: map_res!(call!(util::until_colon), std::str::from_utf8) >>
+    (returns.trim().to_string())
+));
+
+/// Turn the raw parameters of a signature into parameters.
+/// The `/` and `*` markers are not parameters, they change the kind of
+/// the parameters before and after them.
+fn parameters(params: Vec<String>) -> Vec<Parameter> {
+    let positional_only = params.iter().position(|x| *x == "/").unwrap_or(0);
+
+    let mut result = Vec::new();
+    let mut keyword_only = false;
+
+    for (index, param) in params.iter().enumerate() {
+        if *param == "/" {
+            continue;
+        } else if *param == "*" {
+            keyword_only = true;
+            continue;
+        }
+
+        let mut parameter = Parameter::from_source(param);
+
+        match parameter.kind {
+            ParameterKind::VarPositional => keyword_only = true,
+            ParameterKind::Normal if index < positional_only => parameter.kind = ParameterKind::PositionalOnly,
+            ParameterKind::Normal if keyword_only => parameter.kind = ParameterKind::KeywordOnly,
+            _ => {}
+        }
+
+        result.push(parameter);
+    }
+
+    result
+}
+
+// A decorator line, without the @. The indentation of the next line is
+// consumed too, so that decorated methods line up with their def.
 named!(decorator<String>,
     do_parse!(
         tag!("@") >>
         decorator_string: map_res!(take_until_and_consume!("\n"), std::str::from_utf8) >>
+        call!(util::indentation) >>
 
-        (decorator_string.to_string())
+        (decorator_string.trim().to_string())
     )
 );
 
@@ -264,33 +478,213 @@ named!(doc_string<String>,
             ),
             std::str::from_utf8
         ) >>
-        (doc_string.trim().to_string())
+        (doc_string.replace("\r\n", "\n").trim().to_string())
     )
 );
 
-pub fn parse(source: &[u8]) -> Vec<Item> {
+/// Parse a python module.
+/// Returns the byte offset where parsing stopped if the source could not
+/// be parsed completely.
+pub fn parse(source: &[u8]) -> Result<Vec<Item>, usize> {
     let mut result: Vec<Item> = Vec::new();
 
     // Module doc string should be the first statement in the module.
-    let item_module_doc_string_result = item_module_doc_string(source).unwrap();
-    result.push(item_module_doc_string_result.1);
+    let remaining = match item_module_doc_string(source) {
+        nom::IResult::Done(remaining, output) => {
+            result.push(output);
+            remaining
+        },
+        _ => {
+            result.push(Item {
+                node: ItemKind::Module {
+                    description: None
+                }
+            });
+            source
+        }
+    };
 
-    // items will parse the entire code. It is a custom nom parser method.
     // Pass the remaining values to the items parser.
-    let items_result = items(item_module_doc_string_result.0);
+    let (items_result, remaining) = parse_items(source, remaining);
+
+    if !remaining.iter().all(|x| (*x as char).is_whitespace()) {
+        return Err(source.len() - remaining.len());
+    }
 
-    let items_result = match items_result {
-        nom::IResult::Done(_, output) => output,
-        nom::IResult::Error(e) => panic!("Unable to parse [error] {}", e),
-        nom::IResult::Incomplete(n) => {
-            println!("Unable to parse [Incomplete] {:?}", n);
-            Vec::new()
+    result.extend(items_result.into_iter().map(|x| x.item));
+
+    Ok(result)
+}
+
+/// A top level item along with the byte range it occupies in the source.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SpannedItem {
+    pub item: Item,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The width of an indentation with a tab size of 8 and with a tab size of 1.
+fn indentation_widths(indent: &[u8]) -> (usize, usize) {
+    let width = match util::indentation(indent) {
+        nom::IResult::Done(_, width) => width,
+        _ => 0
+    };
+
+    (width, indent.len())
+}
+
+/// Scan a line for the brackets and strings that continue on the next line.
+/// Returns true if the line ends with a backslash continuation.
+fn scan_line(line: &[u8], depth: &mut usize, quote: &mut Option<&'static [u8]>) -> bool {
+    let mut idx = 0;
+
+    while idx < line.len() {
+        if let Some(delimiter) = *quote {
+            if line[idx..].starts_with(delimiter) {
+                *quote = None;
+                idx += delimiter.len();
+            } else {
+                idx += if line[idx] == b'\\' { 2 } else { 1 };
+            }
+            continue;
         }
+
+        match line[idx] {
+            b'#' => return false,
+            b'(' | b'[' | b'{' => *depth += 1,
+            b')' | b']' | b'}' => *depth = depth.saturating_sub(1),
+            b'\\' if idx + 1 == line.len() => return true,
+            b'"' | b'\'' => {
+                let delimiter: &'static [u8] = if line[idx..].starts_with(b"\"\"\"") {
+                    b"\"\"\""
+                } else if line[idx..].starts_with(b"'''") {
+                    b"'''"
+                } else if line[idx] == b'"' {
+                    b"\""
+                } else {
+                    b"'"
+                };
+                *quote = Some(delimiter);
+                idx += delimiter.len();
+                continue;
+            },
+            _ => {}
+        }
+
+        idx += 1;
+    }
+
+    // A string in single quotes ends with the line.
+    if let Some(delimiter) = *quote {
+        if delimiter.len() == 1 {
+            *quote = None;
+        }
+    }
+
+    false
+}
+
+/// The width of the indentation of a line in columns. A tab advances to the
+/// next multiple of the tab size, like in python.
+pub fn indentation_width(line: &[u8]) -> usize {
+    match util::indentation(line) {
+        nom::IResult::Done(_, width) => width,
+        _ => 0
+    }
+}
+
+/// Check that tabs and spaces are used consistently in the indentation.
+/// Like python, comparing the indentation of two lines has to give the same
+/// result whether a tab counts as 8 columns or as 1.
+/// Returns the line, starting at 1, of the first inconsistent indentation.
+pub fn check_indentation(source: &[u8]) -> Result<(), usize> {
+    // The widths of the open blocks.
+    let mut levels = vec![(0, 0)];
+    let mut depth = 0;
+    let mut quote = None;
+    let mut continued = false;
+
+    for (number, line) in source.split(|x| *x == b'\n').enumerate() {
+        let line = if line.ends_with(b"\r") { &line[..line.len() - 1] } else { line };
+        let indent_len = line.iter().take_while(|x| **x == b' ' || **x == b'\t').count();
+        let content = &line[indent_len..];
+
+        // Blank lines, comments and continuation lines don't start a statement.
+        let is_statement = !content.is_empty() && content[0] != b'#';
+        if is_statement && depth == 0 && quote.is_none() && !continued {
+            let (width, alt_width) = indentation_widths(&line[..indent_len]);
+            let (top, alt_top) = levels[levels.len() - 1];
+
+            if width > top {
+                if alt_width <= alt_top {
+                    return Err(number + 1);
+                }
+                levels.push((width, alt_width));
+            } else {
+                while levels.len() > 1 && width < levels[levels.len() - 1].0 {
+                    levels.pop();
+                }
+
+                let (top, alt_top) = levels[levels.len() - 1];
+                if width == top && alt_width != alt_top {
+                    return Err(number + 1);
+                }
+            }
+        }
+
+        continued = scan_line(content, &mut depth, &mut quote);
+    }
+
+    Ok(())
+}
+
+/// Parse the source one top level item at a time, keeping track of where
+/// each item starts and ends. The module doc string is not included.
+/// Returns the byte offset where parsing stopped if the source could not
+/// be parsed completely.
+pub fn parse_spanned(source: &[u8]) -> Result<Vec<SpannedItem>, usize> {
+    let input = match item_module_doc_string(source) {
+        nom::IResult::Done(remaining, _) => remaining,
+        _ => source
     };
 
-    result.extend(items_result);
+    let (items, remaining) = parse_items(source, input);
+    if !remaining.iter().all(|x| (*x as char).is_whitespace()) {
+        return Err(source.len() - remaining.len());
+    }
 
-    result
+    Ok(items)
+}
+
+/// Parse the items in input, which is the tail of source.
+/// Returns the items and the input that is left after the last item.
+fn parse_items<'a>(source: &[u8], input: &'a [u8]) -> (Vec<SpannedItem>, &'a [u8]) {
+    let mut result: Vec<SpannedItem> = Vec::new();
+    let mut input = input;
+
+    while !input.is_empty() {
+        let start = source.len() - input.len();
+
+        match item(input) {
+            nom::IResult::Done(remaining, output) => {
+                // Guard against parsers that succeed without consuming input.
+                if remaining.len() == input.len() {
+                    break;
+                }
+
+                result.push(SpannedItem {
+                    item: output,
+                    start: start,
+                    end: source.len() - remaining.len()
+                });
+                input = remaining;
+            },
+            _ => break
+        }
+    }
+
+    (result, input)
 }
 
 #[test]
@@ -319,7 +713,12 @@ import os
 
     let import_os = Item {
         node: ItemKind::Import {
-            path: "os".to_string()
+            imports: vec!(Import {
+                module: None,
+                name: "os".to_string(),
+                alias: None,
+                level: 0
+            })
         }
     };
 
@@ -337,10 +736,13 @@ from os import stat
     let actual_result = item_import_from(content.as_bytes());
 
     let import_os = Item {
-        node: ItemKind::ImportFrom {
-            module: "os".to_string(),
-            name: "stat".to_string(),
-            level: 0
+        node: ItemKind::Import {
+            imports: vec!(Import {
+                module: Some("os".to_string()),
+                name: "stat".to_string(),
+                alias: None,
+                level: 0
+            })
         }
     };
 
@@ -358,10 +760,13 @@ from .os import stat
     let actual_result = item_import_from(content.as_bytes());
 
     let import_os = Item {
-        node: ItemKind::ImportFrom {
-            module: "os".to_string(),
-            name: "stat".to_string(),
-            level: 1
+        node: ItemKind::Import {
+            imports: vec!(Import {
+                module: Some("os".to_string()),
+                name: "stat".to_string(),
+                alias: None,
+                level: 1
+            })
         }
     };
 
@@ -379,10 +784,13 @@ from ..os.stat import __init__
     let actual_result = item_import_from(content.as_bytes());
 
     let import_os = Item {
-        node: ItemKind::ImportFrom {
-            module: "os.stat".to_string(),
-            name: "__init__".to_string(),
-            level: 2
+        node: ItemKind::Import {
+            imports: vec!(Import {
+                module: Some("os.stat".to_string()),
+                name: "__init__".to_string(),
+                alias: None,
+                level: 2
+            })
         }
     };
 
@@ -408,13 +816,23 @@ def hello():
 
     let import_os = Item {
         node: ItemKind::Import {
-            path: "os".to_string()
+            imports: vec!(Import {
+                module: None,
+                name: "os".to_string(),
+                alias: None,
+                level: 0
+            })
         }
     };
 
     let import_imap = Item {
         node: ItemKind::Import {
-            path: "imap".to_string()
+            imports: vec!(Import {
+                module: None,
+                name: "imap".to_string(),
+                alias: None,
+                level: 0
+            })
         }
     };
 
@@ -422,6 +840,9 @@ def hello():
         node: ItemKind::Function {
             name: "hello".to_string(),
             description: Some("Hello function.".to_string()),
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
             parameters: Vec::new()
         }
     };
@@ -465,13 +886,19 @@ class Animal:
     let method = Function {
         name: "__init__".to_string(),
         description: None,
-        parameters: vec!("self".to_string())
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec!(Parameter::new("self"))
     };
 
     let item_kind = ItemKind::Class {
         name: "Animal".to_string(),
         description: None,
         parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
         methods: vec!(method)
     };
 
@@ -492,13 +919,16 @@ class Animal(Object):
 
     let result = item_class(class_content.as_bytes());
 
-    let mut params: Vec<String> = Vec::new();
+    let mut params: Vec<Parameter> = Vec::new();
 
-    params.push("self".to_string());
+    params.push(Parameter::new("self"));
 
     let method = Function {
         name: "__init__".to_string(),
         description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
         parameters: params
     };
 
@@ -506,6 +936,9 @@ class Animal(Object):
         name: "Animal".to_string(),
         description: None,
         parents: vec!("Object".to_string()),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
         methods: vec!(method)
     };
 
@@ -528,13 +961,16 @@ class Animal:
 
     let result = item_class(class_content.as_bytes());
 
-    let mut params: Vec<String> = Vec::new();
+    let mut params: Vec<Parameter> = Vec::new();
 
-    params.push("self".to_string());
+    params.push(Parameter::new("self"));
 
     let method = Function {
         name: "__init__".to_string(),
         description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
         parameters: params
     };
 
@@ -542,6 +978,14 @@ class Animal:
         name: "Animal".to_string(),
         description: None,
         parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: vec!(Attribute {
+            name: "start".to_string(),
+            annotation: None,
+            default: Some("True".to_string()),
+            origin: AttributeOrigin::Class
+        }),
         methods: vec!(method)
     };
 
@@ -552,6 +996,48 @@ class Animal:
     assert_eq!(result.unwrap().1, expected_result);
 }
 
+#[test]
+fn test_parser_assignments_in_annotations() {
+    let content = r#"
+LIMIT: Annotated[int, Field(gt=0)] = 1
+
+class Animal:
+    legs: Annotated[int, Field(ge=0)] = 4
+"#;
+
+    let result = items(content.as_bytes());
+
+    let expected_result = vec!(
+        Item {
+            node: ItemKind::Variable {
+                variable: Variable {
+                    name: "LIMIT".to_string(),
+                    annotation: Some("Annotated[int, Field(gt=0)]".to_string()),
+                    value: Some("1".to_string())
+                }
+            }
+        },
+        Item {
+            node: ItemKind::Class {
+                name: "Animal".to_string(),
+                description: None,
+                parents: Vec::new(),
+                decorators: Vec::new(),
+                classes: Vec::new(),
+                attributes: vec!(Attribute {
+                    name: "legs".to_string(),
+                    annotation: Some("Annotated[int, Field(ge=0)]".to_string()),
+                    default: Some("4".to_string()),
+                    origin: AttributeOrigin::Class
+                }),
+                methods: Vec::new()
+            }
+        }
+    );
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
 #[test]
 fn test_parser_class_with_multiple_methods() {
     let class_content = r#"
@@ -574,26 +1060,35 @@ class Animal:
 
     let result = item_class(class_content.as_bytes());
 
-    let mut params: Vec<String> = Vec::new();
+    let mut params: Vec<Parameter> = Vec::new();
 
-    params.push("self".to_string());
+    params.push(Parameter::new("self"));
 
     let method1 = Function {
         name: "__init__".to_string(),
         description: Some("Init method.".to_string()),
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
         parameters: params
     };
 
     let method2 = Function {
         name: "hello".to_string(),
         description: Some("Hello method.".to_string()),
-        parameters: vec!["args".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("args")]
     };
 
     let item_kind = ItemKind::Class {
         name: "Animal".to_string(),
         description: Some("Animal class.".to_string()),
         parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
         methods: vec!(method1, method2)
     };
 
@@ -619,7 +1114,10 @@ def hello(args):
         node: ItemKind::Function {
             name: "hello".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
         }
     };
 
@@ -642,7 +1140,10 @@ def hello(args):
         node: ItemKind::Function {
             name: "hello".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
         }
     });
 
@@ -667,7 +1168,10 @@ def hello(args):
         node: ItemKind::Function {
             name: "hello".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: vec!("test1".to_string(), "test2".to_string()),
+            parameters: vec!(Parameter::new("args"))
         }
     };
 
@@ -691,7 +1195,10 @@ def hello(args, ):
         node: ItemKind::Function {
             name: "hello".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
         }
     };
 
@@ -714,7 +1221,10 @@ def __hello__(args):
         node: ItemKind::Function {
             name: "__hello__".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
         }
     };
 
@@ -737,7 +1247,15 @@ def __hello__(args, display=True):
         node: ItemKind::Function {
             name: "__hello__".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string(), "display=True".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"), Parameter {
+                name: "display".to_string(),
+                annotation: None,
+                default: Some("True".to_string()),
+                kind: ParameterKind::Normal
+            })
         }
     };
     println!("The result is: {:?}", result);
@@ -768,7 +1286,10 @@ def hello(args):
         node: ItemKind::Function {
             name: "__hello__".to_string(),
             description: Some("This is the hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
         }
     };
 
@@ -776,7 +1297,10 @@ def hello(args):
         node: ItemKind::Function {
             name: "hello".to_string(),
             description: Some("Another hello function.".to_string()),
-            parameters: vec!("args".to_string())
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
         }
     };
 
@@ -816,13 +1340,19 @@ def display(msg):
     let init_method = Function {
         name: "__init__".to_string(),
         description: Some("Init method.".to_string()),
-        parameters: vec!["self".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
     };
 
     let get_animal_method = Function {
         name: "get_animal".to_string(),
         description: Some("Get the animal instance of this object.".to_string()),
-        parameters: vec!["self".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
     };
 
     let class_item = Item {
@@ -830,6 +1360,9 @@ def display(msg):
             name: "Animal".to_string(),
             description: Some("This is the animal class.".to_string()),
             parents: Vec::new(),
+            decorators: Vec::new(),
+            classes: Vec::new(),
+            attributes: Vec::new(),
             methods: vec!(init_method, get_animal_method)
         }
     };
@@ -840,7 +1373,10 @@ def display(msg):
         node: ItemKind::Function {
             name: "display".to_string(),
             description: Some("This is the display function.".to_string()),
-            parameters: vec!["msg".to_string()]
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec![Parameter::new("msg")]
         }
     };
     expected_result.push(function_item);
@@ -874,13 +1410,19 @@ class Animal:
     let init_method = Function {
         name: "__init__".to_string(),
         description: Some("Init method.".to_string()),
-        parameters: vec!["self".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
     };
 
     let get_animal_method = Function {
         name: "get_animal".to_string(),
         description: Some("Get the animal instance of this object.".to_string()),
-        parameters: vec!["self".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
     };
 
     let class_item = Item {
@@ -888,6 +1430,9 @@ class Animal:
             name: "Animal".to_string(),
             description: Some("This is the animal class.".to_string()),
             parents: Vec::new(),
+            decorators: Vec::new(),
+            classes: Vec::new(),
+            attributes: vec!(Attribute::from_source("copy = __copy__", AttributeOrigin::Class).unwrap()),
             methods: vec!(init_method, get_animal_method)
         }
     };
@@ -935,4 +1480,825 @@ fn test_parser_doc_string_with_single_quotes() {
     let result = doc_string(doc_string_content.as_bytes());
 
     assert_eq!(result.unwrap().1, "This is the description \"string'.");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parser_parse_spanned() {
+    let content = r#""""
+Module doc string.
+"""
+class Animal:
+    def __init__(self):
+        pass
+
+def hello(args):
+    return args
+"#;
+
+    let result = parse_spanned(content.as_bytes()).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(&content[result[0].start..result[0].end], r#"
+class Animal:
+    def __init__(self):
+        pass
+
+"#);
+    assert_eq!(&content[result[1].start..result[1].end], r#"def hello(args):
+    return args
+"#);
+
+    match result[1].item.node {
+        ItemKind::Function {ref name, ..} => assert_eq!(name, "hello"),
+        _ => panic!("Expected a function")
+    }
+
+    let invalid = "def hello(args):\n    return args\n\ndef broken(:\n    pass\n";
+    assert_eq!(parse_spanned(invalid.as_bytes()), Err(invalid.find("def broken").unwrap()));
+}
+
+#[test]
+fn test_parser_item_fn_with_annotations_and_parameter_kinds() {
+    let fn_content = r#"
+def hello(path: str, /, mode="r", *args, timeout: int = 30, **kwargs):
+    pass
+"#;
+
+    let result = item_fn(fn_content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Function {
+            name: "hello".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(
+                Parameter {
+                    name: "path".to_string(),
+                    annotation: Some("str".to_string()),
+                    default: None,
+                    kind: ParameterKind::PositionalOnly
+                },
+                Parameter {
+                    name: "mode".to_string(),
+                    annotation: None,
+                    default: Some("\"r\"".to_string()),
+                    kind: ParameterKind::Normal
+                },
+                Parameter {
+                    name: "args".to_string(),
+                    annotation: None,
+                    default: None,
+                    kind: ParameterKind::VarPositional
+                },
+                Parameter {
+                    name: "timeout".to_string(),
+                    annotation: Some("int".to_string()),
+                    default: Some("30".to_string()),
+                    kind: ParameterKind::KeywordOnly
+                },
+                Parameter {
+                    name: "kwargs".to_string(),
+                    annotation: None,
+                    default: None,
+                    kind: ParameterKind::VarKeyword
+                }
+            )
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_item_fn_with_assignments_in_annotations() {
+    let fn_content = r#"
+def search(q: Annotated[str, Query(max_length=50)] = None, sep: Literal["k=v"] = "a=b"):
+    pass
+"#;
+
+    let result = item_fn(fn_content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Function {
+            name: "search".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(
+                Parameter {
+                    name: "q".to_string(),
+                    annotation: Some("Annotated[str, Query(max_length=50)]".to_string()),
+                    default: Some("None".to_string()),
+                    kind: ParameterKind::Normal
+                },
+                Parameter {
+                    name: "sep".to_string(),
+                    annotation: Some("Literal[\"k=v\"]".to_string()),
+                    default: Some("\"a=b\"".to_string()),
+                    kind: ParameterKind::Normal
+                }
+            )
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_item_fn_with_keyword_only_marker() {
+    let fn_content = r#"
+def hello(a, *, b=None):
+    pass
+"#;
+
+    let result = item_fn(fn_content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Function {
+            name: "hello".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(
+                Parameter::new("a"),
+                Parameter {
+                    name: "b".to_string(),
+                    annotation: None,
+                    default: Some("None".to_string()),
+                    kind: ParameterKind::KeywordOnly
+                }
+            )
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_item_fn_with_return_annotation() {
+    let fn_content = r#"
+def hello(args) -> Dict[str, int]:
+    """
+    This is the hello function.
+    """
+    pass
+"#;
+
+    let result = item_fn(fn_content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Function {
+            name: "hello".to_string(),
+            description: Some("This is the hello function.".to_string()),
+            returns: Some("Dict[str, int]".to_string()),
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("args"))
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_item_fn_with_colon_in_return_annotation() {
+    let fn_content = r#"
+def hello(args) -> "Dict[str, 'a:b']":
+    pass
+
+def slice(args) -> Annotated[int, x[1:2]]:
+    pass
+"#;
+
+    let result = items(fn_content.as_bytes()).unwrap().1;
+    let returns: Vec<Option<String>> = result.into_iter().map(|x| match x.node {
+        ItemKind::Function {returns, ..} => returns,
+        node => panic!("Expected a function, got {:?}", node)
+    }).collect();
+
+    assert_eq!(returns, vec!(
+        Some("\"Dict[str, 'a:b']\"".to_string()),
+        Some("Annotated[int, x[1:2]]".to_string())
+    ));
+}
+
+#[test]
+fn test_parser_parse_error_offset() {
+    let content = "import os\nx = 1";
+
+    assert_eq!(parse(content.as_bytes()), Err(10));
+}
+
+#[test]
+fn test_parser_item_fn_async() {
+    let fn_content = r#"
+async def handler(request):
+    """
+    Handle the request.
+    """
+    pass
+"#;
+
+    let result = item_fn(fn_content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Function {
+            name: "handler".to_string(),
+            description: Some("Handle the request.".to_string()),
+            returns: None,
+            is_async: true,
+            decorators: Vec::new(),
+            parameters: vec!(Parameter::new("request"))
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_class_with_async_method() {
+    let class_content = r#"
+class Client:
+    async def fetch(self, url):
+        pass
+
+    def close(self):
+        pass
+"#;
+
+    let result = item_class(class_content.as_bytes());
+
+    let fetch = Function {
+        name: "fetch".to_string(),
+        description: None,
+        returns: None,
+        is_async: true,
+        decorators: Vec::new(),
+        parameters: vec!(Parameter::new("self"), Parameter::new("url"))
+    };
+
+    let close = Function {
+        name: "close".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec!(Parameter::new("self"))
+    };
+
+    let expected_result = Item {
+        node: ItemKind::Class {
+            name: "Client".to_string(),
+            description: None,
+            parents: Vec::new(),
+            decorators: Vec::new(),
+            classes: Vec::new(),
+            attributes: Vec::new(),
+            methods: vec!(fetch, close)
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_class_with_decorators() {
+    let class_content = r#"
+@dataclass(frozen=True)
+class Animal:
+    @property
+    def name(self):
+        pass
+
+    @staticmethod
+    @cache
+    def create():
+        pass
+"#;
+
+    let result = item_class(class_content.as_bytes());
+
+    let name = Function {
+        name: "name".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: vec!("property".to_string()),
+        parameters: vec!(Parameter::new("self"))
+    };
+
+    let create = Function {
+        name: "create".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: vec!("staticmethod".to_string(), "cache".to_string()),
+        parameters: Vec::new()
+    };
+
+    let expected_result = Item {
+        node: ItemKind::Class {
+            name: "Animal".to_string(),
+            description: None,
+            parents: Vec::new(),
+            decorators: vec!("dataclass(frozen=True)".to_string()),
+            classes: Vec::new(),
+            attributes: Vec::new(),
+            methods: vec!(name, create)
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_class_with_instance_attributes() {
+    let class_content = r#"
+class Animal:
+    legs: int = 4
+    sound: str
+
+    def __init__(self, name, legs):
+        self.name = name
+        self.legs = legs
+        self.owner: Optional[str] = None
+        self.tags = [
+            "pet",
+        ]
+        if name:
+            self.nickname = name.lower()
+        self.name += "!"
+
+    def speak(self):
+        self.spoken = True
+"#;
+
+    let result = item_class(class_content.as_bytes());
+
+    let attributes = match result.unwrap().1.node {
+        ItemKind::Class {attributes, ..} => attributes,
+        _ => panic!("Expected a class")
+    };
+
+    assert_eq!(attributes, vec!(
+        Attribute::from_source("legs: int = 4", AttributeOrigin::Class).unwrap(),
+        Attribute::from_source("sound: str", AttributeOrigin::Class).unwrap(),
+        Attribute::from_source("name = name", AttributeOrigin::Instance).unwrap(),
+        Attribute::from_source("owner: Optional[str] = None", AttributeOrigin::Instance).unwrap(),
+        Attribute::from_source("nickname = name.lower()", AttributeOrigin::Instance).unwrap()
+    ));
+}
+
+#[test]
+fn test_parser_items_with_variables_and_exports() {
+    let content = r#"
+__all__ = [
+    "connect",
+    'MAX_RETRIES',
+]
+
+MAX_RETRIES: int = 3
+TIMEOUTS = {"read": 10, "write": 5}
+x += 1
+if __name__ == "__main__":
+    main()
+"#;
+
+    let result = items(content.as_bytes());
+
+    let expected_result = vec!(
+        Item {
+            node: ItemKind::Exports {
+                names: vec!("connect".to_string(), "MAX_RETRIES".to_string())
+            }
+        },
+        Item {
+            node: ItemKind::Variable {
+                variable: Variable {
+                    name: "MAX_RETRIES".to_string(),
+                    annotation: Some("int".to_string()),
+                    value: Some("3".to_string())
+                }
+            }
+        },
+        Item {
+            node: ItemKind::Variable {
+                variable: Variable::from_source("TIMEOUTS = {\"read\": 10, \"write\": 5}").unwrap()
+            }
+        },
+        Item {
+            node: ItemKind::Code {code: "x += 1".to_string()}
+        },
+        Item {
+            node: ItemKind::Code {code: "if __name__ == \"__main__\":".to_string()}
+        },
+        Item {
+            node: ItemKind::Code {code: "    main()".to_string()}
+        }
+    );
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_items_with_brackets_in_strings() {
+    let content = r#"
+x = "("
+BRACKETS = [')', "]"]  # {
+"#;
+
+    let result = items(content.as_bytes());
+
+    let expected_result = vec!(
+        Item {
+            node: ItemKind::Variable {
+                variable: Variable::from_source("x = \"(\"").unwrap()
+            }
+        },
+        Item {
+            node: ItemKind::Variable {
+                variable: Variable::from_source("BRACKETS = [')', \"]\"]  # {").unwrap()
+            }
+        }
+    );
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_import_with_aliases() {
+    let content = r#"
+import numpy as np, os.path
+"#;
+    let actual_result = item_import(content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Import {
+            imports: vec!(
+                Import {
+                    module: None,
+                    name: "numpy".to_string(),
+                    alias: Some("np".to_string()),
+                    level: 0
+                },
+                Import {
+                    module: None,
+                    name: "os.path".to_string(),
+                    alias: None,
+                    level: 0
+                }
+            )
+        }
+    };
+
+    assert_eq!(actual_result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_import_from_parenthesized() {
+    let content = r#"
+from . import (
+    path,  # comment
+    sep as separator,
+)
+"#;
+    let actual_result = item_import_from(content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Import {
+            imports: vec!(
+                Import {
+                    module: Some("".to_string()),
+                    name: "path".to_string(),
+                    alias: None,
+                    level: 1
+                },
+                Import {
+                    module: Some("".to_string()),
+                    name: "sep".to_string(),
+                    alias: Some("separator".to_string()),
+                    level: 1
+                }
+            )
+        }
+    };
+
+    assert_eq!(actual_result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_class_with_nested_classes() {
+    let class_content = r#"
+class Animal(Model):
+    name = CharField()
+
+    class Meta:
+        ordering = ["name"]
+
+        class Inner:
+            pass
+
+    def speak(self):
+        pass
+"#;
+
+    let result = item_class(class_content.as_bytes());
+
+    let inner = Class {
+        name: "Inner".to_string(),
+        description: None,
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        attributes: Vec::new(),
+        methods: Vec::new(),
+        classes: Vec::new()
+    };
+
+    let meta = Class {
+        name: "Meta".to_string(),
+        description: None,
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        attributes: vec!(Attribute::from_source("ordering = [\"name\"]", AttributeOrigin::Class).unwrap()),
+        methods: Vec::new(),
+        classes: vec!(inner)
+    };
+
+    let speak = Function {
+        name: "speak".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec!(Parameter::new("self"))
+    };
+
+    let expected_result = Item {
+        node: ItemKind::Class {
+            name: "Animal".to_string(),
+            description: None,
+            parents: vec!("Model".to_string()),
+            decorators: Vec::new(),
+            attributes: vec!(Attribute::from_source("name = CharField()", AttributeOrigin::Class).unwrap()),
+            methods: vec!(speak),
+            classes: vec!(meta)
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_item_fn_with_multi_line_signature() {
+    let fn_content = r#"
+def hello(
+    path: str,  # where to look, (or not)
+    b=(1, 2),
+    c="x,y",
+    d: Dict[str, int] = {"a": 1, "b": (2, 3)},
+    e='it''s',
+    f=call(
+        1,
+        2,
+    ),
+) -> int:
+    """
+    This is the hello function.
+    """
+    pass
+"#;
+
+    let result = item_fn(fn_content.as_bytes());
+
+    let expected_result = Item {
+        node: ItemKind::Function {
+            name: "hello".to_string(),
+            description: Some("This is the hello function.".to_string()),
+            returns: Some("int".to_string()),
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec!(
+                Parameter::from_source("path: str"),
+                Parameter::from_source("b=(1, 2)"),
+                Parameter::from_source("c=\"x,y\""),
+                Parameter {
+                    name: "d".to_string(),
+                    annotation: Some("Dict[str, int]".to_string()),
+                    default: Some("{\"a\": 1, \"b\": (2, 3)}".to_string()),
+                    kind: ParameterKind::Normal
+                },
+                Parameter::from_source("e='it''s'"),
+                Parameter::from_source("f=call(1, 2,)")
+            )
+        }
+    };
+
+    assert_eq!(result.unwrap().1, expected_result);
+}
+
+#[test]
+fn test_parser_class_with_multi_line_method_signature() {
+    let class_content = r#"
+class Animal:
+    def __init__(self,
+                 name="a)b",
+                 *, legs=4):
+        pass
+
+    def speak(self):
+        pass
+"#;
+
+    let result = item_class(class_content.as_bytes());
+
+    let methods = match result.unwrap().1.node {
+        ItemKind::Class {methods, ..} => methods,
+        _ => panic!("Expected a class")
+    };
+
+    assert_eq!(methods.len(), 2);
+    assert_eq!(methods[0].parameters, vec!(
+        Parameter::new("self"),
+        Parameter::from_source("name=\"a)b\""),
+        Parameter {
+            name: "legs".to_string(),
+            annotation: None,
+            default: Some("4".to_string()),
+            kind: ParameterKind::KeywordOnly
+        }
+    ));
+    assert_eq!(methods[1].name, "speak");
+}
+
+#[test]
+fn test_parser_parse_tab_indentation() {
+    let content = r#"
+class Client:
+    """A client."""
+    retries = 3
+
+    @property
+    def url(self):
+        if self.secure:
+            return "https://"
+        return "http://"
+
+    def close(self):
+        pass
+
+def connect():
+    return Client()
+"#;
+
+    // A tab goes to the next multiple of 8, so a tab and 8 spaces are the same level.
+    let tabs = content.replace("        ", "\t").replace("    ", "\t");
+    let mixed = content.replace("        ", "\t");
+
+    assert_eq!(parse(tabs.as_bytes()), parse(content.as_bytes()));
+    assert_eq!(parse(mixed.as_bytes()), parse(content.as_bytes()));
+}
+
+#[test]
+fn test_parser_parse_crlf() {
+    let content = r#"#! /usr/bin/env python
+"""
+The client module.
+"""
+from os import (
+    path,
+    stat,
+)
+
+TIMEOUT = 10
+
+class Client:
+    """
+    A client.
+    """
+
+    def close(self):
+        pass
+
+def connect(host,
+            port):
+    pass
+"#;
+
+    let crlf = content.replace("\n", "\r\n");
+
+    assert_eq!(parse(crlf.as_bytes()), parse(content.as_bytes()));
+}
+
+#[test]
+fn test_parser_check_indentation() {
+    assert_eq!(check_indentation(b"class A:\n\tdef f(self):\n\t\tpass\n"), Ok(()));
+    assert_eq!(check_indentation(b"class A:\r\n    def f(self):\r\n        pass\r\n"), Ok(()));
+
+    // A tab is 8 columns, but the second line is only indented by 4.
+    assert_eq!(check_indentation(b"if x:\n\ty = 1\n        z = 2\n"), Err(3));
+    assert_eq!(check_indentation(b"class A:\n        def f(self):\n\t    pass\n"), Err(3));
+
+    // Only the start of a statement counts, not its continuation lines.
+    assert_eq!(check_indentation(b"def f(a,\n\t  b):\n    x = \"\"\"\n\t  \"\"\"\n    pass\n"), Ok(()));
+}
+
+#[test]
+fn test_parser_ident() {
+    assert_eq!(util::ident(b"sha256(data)"), nom::IResult::Done(&b"(data)"[..], &b"sha256"[..]));
+    assert_eq!(util::ident(b"_private:"), nom::IResult::Done(&b":"[..], &b"_private"[..]));
+    assert_eq!(util::ident("größe()".as_bytes()), nom::IResult::Done(&b"()"[..], "größe".as_bytes()));
+    assert_eq!(util::ident("名前 = 1".as_bytes()), nom::IResult::Done(&b" = 1"[..], "名前".as_bytes()));
+    assert!(util::ident(b"3d").is_err());
+    assert!(util::ident("€uro".as_bytes()).is_err());
+}
+
+#[test]
+fn test_parser_unicode_and_digit_identifiers() {
+    let content = r#"
+class Base64Encoder(Py3Compat):
+    def sha256(self, data):
+        pass
+
+def größe(maß):
+    pass
+"#;
+
+    let result = parse(content.as_bytes()).unwrap();
+
+    match result[1].node {
+        ItemKind::Class {ref name, ref parents, ref methods, ..} => {
+            assert_eq!(name, "Base64Encoder");
+            assert_eq!(parents, &vec!["Py3Compat".to_string()]);
+            assert_eq!(methods[0].name, "sha256");
+        },
+        ref node => panic!("Expected a class, got {:?}", node)
+    }
+
+    match result[2].node {
+        ItemKind::Function {ref name, ref parameters, ..} => {
+            assert_eq!(name, "größe");
+            assert_eq!(parameters, &vec![Parameter::new("maß")]);
+        },
+        ref node => panic!("Expected a function, got {:?}", node)
+    }
+}
+
+#[test]
+fn test_parser_parse_stub() {
+    let content = r#"
+from typing import Generic, TypeVar, overload
+
+T = TypeVar("T")
+MAX_SIZE: int
+
+class Empty: ...
+
+class Box(Generic[T], metaclass=ABCMeta):
+    value: T
+    def get(self) -> T: ...
+    @overload
+    def put(self, value: int) -> None: ...
+    @overload
+    def put(self, value: str) -> None: ...
+
+def open_box(box: Box[T], force: bool = ...) -> T: ...
+"#;
+
+    let result = parse(content.as_bytes()).unwrap();
+    let nodes = result.into_iter().map(|x| x.node).collect::<Vec<_>>();
+
+    assert_eq!(nodes[3], ItemKind::Variable {variable: Variable::from_source("MAX_SIZE: int").unwrap()});
+
+    match nodes[4] {
+        ItemKind::Class {ref name, ref methods, ..} => {
+            assert_eq!(name, "Empty");
+            assert!(methods.is_empty());
+        },
+        ref node => panic!("Expected a class, got {:?}", node)
+    }
+
+    match nodes[5] {
+        ItemKind::Class {ref name, ref parents, ref attributes, ref methods, ..} => {
+            assert_eq!(name, "Box");
+            assert_eq!(parents, &vec!["Generic[T]".to_string(), "metaclass=ABCMeta".to_string()]);
+            assert_eq!(attributes, &vec![Attribute::from_source("value: T", AttributeOrigin::Class).unwrap()]);
+            assert_eq!(methods.iter().map(|x| &x.name[..]).collect::<Vec<_>>(), vec!["get", "put", "put"]);
+            assert_eq!(methods[2].decorators, vec!["overload".to_string()]);
+            assert_eq!(methods[2].parameters[1], Parameter::from_source("value: str"));
+        },
+        ref node => panic!("Expected a class, got {:?}", node)
+    }
+
+    match nodes[6] {
+        ItemKind::Function {ref name, ref parameters, ref returns, ..} => {
+            assert_eq!(name, "open_box");
+            assert_eq!(parameters[1], Parameter::from_source("force: bool = ..."));
+            assert_eq!(returns, &Some("T".to_string()));
+        },
+        ref node => panic!("Expected a function, got {:?}", node)
+    }
+}
@@ -1,69 +1,192 @@
 use std::ops::{Range, RangeFrom, RangeTo};
+use std::str;
 
 use nom::{Slice, Needed, InputLength, InputIter, ErrorKind, AsChar};
 use nom::IResult;
 use nom::IResult::{Done, Incomplete, Error};
+use unicode_xid::UnicodeXID;
 
-/// 0x5F (underscore _)
-/// Checks for valid identifier [A-Za-z_]
-fn is_ident(chr: char) -> bool
-{
-    let chr: u8 = chr as u8;
-    (chr >= 0x41 && chr <= 0x5A) || (chr >= 0x61 && chr <= 0x7A) || (chr == 0x5F)
+/// Checks for the first character of an identifier, XID_Start or _.
+fn is_ident_start(chr: char) -> bool {
+    chr == '_' || UnicodeXID::is_xid_start(chr)
 }
 
-pub fn ident<T>(input: T) -> IResult<T, T> where 
-    T: Slice<Range<usize>>+Slice<RangeFrom<usize>>+Slice<RangeTo<usize>>,
-    T: InputIter+InputLength
-{
-    let input_length = input.input_len();
-    if input_length == 0 {
+/// Checks for the other characters of an identifier, XID_Continue.
+fn is_ident_continue(chr: char) -> bool {
+    UnicodeXID::is_xid_continue(chr)
+}
+
+/// A python identifier, e.g. `sha256` or `größe`.
+/// The input is decoded as UTF-8 up to the first invalid byte.
+pub fn ident(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    if input.is_empty() {
         return Incomplete(Needed::Unknown);
     }
 
-    for (idx, item) in input.iter_indices() {
-        if !is_ident(item.as_char()) {
-            if idx == 0 {
-                return Error(error_position!(ErrorKind::Alpha, input))
-            } else {
-                return Done(input.slice(idx..), input.slice(0..idx))
+    let text = match str::from_utf8(input) {
+        Ok(text) => text,
+        Err(e) => str::from_utf8(&input[..e.valid_up_to()]).unwrap()
+    };
+
+    let mut end = 0;
+    for (idx, chr) in text.char_indices() {
+        let is_valid = if idx == 0 { is_ident_start(chr) } else { is_ident_continue(chr) };
+        if !is_valid {
+            break;
+        }
+        end = idx + chr.len_utf8();
+    }
+
+    if end == 0 {
+        Error(error_position!(ErrorKind::Alpha, input))
+    } else {
+        Done(&input[end..], &input[..end])
+    }
+}
+
+/// The end of the string literal that starts at `start`, after its closing
+/// quote. Returns None if the string is not closed in the input.
+fn string_end(input: &[u8], start: usize) -> Option<usize> {
+    let chr = input[start];
+    let quote = if input[start..].starts_with(&[chr, chr, chr]) { &input[start..start + 3] } else { &input[start..start + 1] };
+    let mut end = start + quote.len();
+    loop {
+        if end >= input.len() {
+            return None;
+        } else if input[end] == b'\\' {
+            end += 2;
+        } else if input[end..].starts_with(quote) {
+            return Some(end + quote.len());
+        } else {
+            end += 1;
+        }
+    }
+}
+
+/// The parameters of a signature, from after the opening ( up to the
+/// closing ), which is left in the input.
+///
+/// Parameters are split on the commas outside of brackets and strings, so
+/// defaults like `(1, 2)` or `"x,y"` and annotations like `Dict[str, int]`
+/// stay whole. Comments are dropped and a parameter that spans several
+/// lines is joined into one line.
+pub fn func_params(input: &[u8]) -> IResult<&[u8], Vec<String>> {
+    let mut params: Vec<String> = Vec::new();
+    let mut current: Vec<u8> = Vec::new();
+    let mut depth = 0;
+    let mut newline = false;
+    let mut idx = 0;
+
+    while idx < input.len() {
+        let chr = input[idx];
+
+        if chr == b'\n' || chr == b'\r' || (newline && (chr == b' ' || chr == b'\t')) {
+            newline = true;
+            idx += 1;
+            continue;
+        }
+
+        // Continuation lines are joined with a single space, except right
+        // after an opening and before a closing bracket.
+        if newline {
+            newline = false;
+            let after_open = current.last().map_or(true, |x| b"([{ ".contains(x));
+            if !after_open && !b")]},".contains(&chr) {
+                current.push(b' ');
             }
         }
+
+        match chr {
+            b'"' | b'\'' => {
+                let end = match string_end(input, idx) {
+                    Some(end) => end,
+                    None => return Incomplete(Needed::Unknown)
+                };
+                current.extend_from_slice(&input[idx..end]);
+                idx = end;
+                continue;
+            },
+            b'#' => {
+                while idx < input.len() && input[idx] != b'\n' {
+                    idx += 1;
+                }
+                continue;
+            },
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => {
+                push_param(&mut params, &current);
+                return Done(&input[idx..], params);
+            },
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                push_param(&mut params, &current);
+                current.clear();
+                idx += 1;
+                continue;
+            },
+            _ => {}
+        }
+
+        current.push(chr);
+        idx += 1;
     }
-    Done(input.slice(input_length..), input)
+
+    Incomplete(Needed::Unknown)
 }
 
-/// 0x5F (underscore _)
-/// Checks for valid identifier [A-Za-z_]
-/// Allow equal to (=) 0x3D
-/// Allow {} 0x7B 0x7D
-/// Allow [] 0x5B 0x5D
-fn is_func_param(chr: char) -> bool
-{
-    let chr: u8 = chr as u8;
-    (chr >= 0x41 && chr <= 0x5A) || (chr >= 0x61 && chr <= 0x7A) || (chr == 0x5F) || (chr == 0x3D)
-    || (chr == 0x7B) || (chr == 0x7D) || (chr == 0x5B) || (chr == 0x5D)
+/// The source up to the `:` that ends a signature, which is left in the
+/// input. Colons in brackets and strings, like the one in
+/// `Dict[str, "a:b"]`, don't end it.
+pub fn until_colon(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let mut depth = 0;
+    let mut idx = 0;
+
+    while idx < input.len() {
+        match input[idx] {
+            b'"' | b'\'' => {
+                idx = match string_end(input, idx) {
+                    Some(end) => end,
+                    None => return Incomplete(Needed::Unknown)
+                };
+                continue;
+            },
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth > 0 => depth -= 1,
+            b':' if depth == 0 => return Done(&input[idx..], &input[..idx]),
+            _ => {}
+        }
+
+        idx += 1;
+    }
+
+    Incomplete(Needed::Unknown)
 }
 
-pub fn func_param<T>(input: T) -> IResult<T, T> where
-    T: Slice<Range<usize>>+Slice<RangeFrom<usize>>+Slice<RangeTo<usize>>,
-    T: InputIter+InputLength
-{
-    let input_length = input.input_len();
-    if input_length == 0 {
-        return Incomplete(Needed::Unknown);
+/// Add a parameter, skipping the empty one after a trailing comma.
+fn push_param(params: &mut Vec<String>, param: &[u8]) {
+    let param = String::from_utf8_lossy(param).trim().to_string();
+    if !param.is_empty() {
+        params.push(param);
     }
+}
 
-    for (idx, item) in input.iter_indices() {
-        if !is_func_param(item.as_char()) {
-            if idx == 0 {
-                return Error(error_position!(ErrorKind::Alpha, input))
-            } else {
-                return Done(input.slice(idx..), input.slice(0..idx))
-            }
+/// A tab advances the indentation to the next multiple of 8, like in python.
+pub const TAB_SIZE: usize = 8;
+
+/// The indentation at the start of the input, as a number of columns.
+/// Never incomplete, a line without indentation has a width of 0.
+pub fn indentation(input: &[u8]) -> IResult<&[u8], usize> {
+    let mut width = 0;
+
+    for (idx, chr) in input.iter().enumerate() {
+        match *chr {
+            b' ' => width += 1,
+            b'\t' => width = (width / TAB_SIZE + 1) * TAB_SIZE,
+            _ => return Done(&input[idx..], width)
         }
     }
-    Done(input.slice(input_length..), input)
+
+    Done(&input[input.len()..], width)
 }
 
 pub fn emptyline<T>(input: T) -> IResult<T, T> where
@@ -85,14 +208,15 @@ pub fn emptyline<T>(input: T) -> IResult<T, T> where
             newline_index = idx;
         }
 
-        if start == true && item_char == ' ' {
+        if start == true && (item_char == ' ' || item_char == '\t' || item_char == '\r') {
             continue;
         }
 
-        if start == true && !(item_char == '\n' || item_char == ' ') {
+        if start == true && item_char != '\n' {
             return Done(input.slice(newline_index..), input.slice(0..newline_index));
-        } else if start == false && (item_char != '\n') {
-            return Done(input.slice(input_length..), input);
+        } else if start == false && item_char != '\n' && item_char != '\r' {
+            // Not an empty line, e.g. the body of `class A: ...`.
+            return Done(input.slice(0..), input.slice(0..0));
         }
     }
     Done(input.slice(input_length..), input)
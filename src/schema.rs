@@ -0,0 +1,317 @@
+use serde_json::{Map, Value};
+
+use structures::CONFIG_VERSION;
+
+/// Draft of the JSON Schema spec the schema is written for.
+const SCHEMA_DRAFT: &'static str = "http://json-schema.org/draft-07/schema#";
+
+fn string(description: &str) -> Value {
+    json!({"type": "string", "description": description})
+}
+
+/// A string that is null when it's not set, like the doc strings.
+fn optional_string(description: &str) -> Value {
+    json!({"type": ["string", "null"], "description": description})
+}
+
+fn array(items: Value, description: &str) -> Value {
+    json!({"type": "array", "items": items, "description": description})
+}
+
+fn reference(definition: &str) -> Value {
+    json!({"$ref": format!("#/definitions/{}", definition)})
+}
+
+/// An object that only has the given properties.
+fn object(description: &str, required: &[&str], properties: Vec<(&str, Value)>) -> Value {
+    let mut map = Map::new();
+    for (name, property) in properties {
+        map.insert(name.to_string(), property);
+    }
+
+    json!({
+        "type": "object",
+        "description": description,
+        "required": required,
+        "properties": map,
+        "additionalProperties": false
+    })
+}
+
+fn version() -> Value {
+    json!({
+        "type": "integer",
+        "minimum": 1,
+        "maximum": CONFIG_VERSION,
+        "description": "Version of the config schema, configs without one are version 1."
+    })
+}
+
+fn validation() -> Value {
+    let rule = |kind: &str| string(&format!("Regex the names of {} have to match.", kind));
+
+    object("Naming rules, the ones that are not set fall back to PEP 8.", &[], vec![
+        ("packages", rule("packages")),
+        ("modules", rule("modules")),
+        ("classes", rule("classes")),
+        ("functions", rule("functions")),
+        ("methods", rule("methods")),
+        ("parameters", rule("parameters")),
+        ("constants", rule("module level variables annotated with Final")),
+    ])
+}
+
+fn root() -> Value {
+    object("The project, a directory of packages and modules.", &["name"], vec![
+        ("name", string("Name of the project directory.")),
+        ("packages", array(reference("package"), "Packages at the top of the project.")),
+        ("modules", array(reference("module"), "Modules at the top of the project.")),
+    ])
+}
+
+fn package() -> Value {
+    object("A python package, a directory with an __init__.py.", &["name"], vec![
+        ("name", string("Name of the package directory.")),
+        ("include", array(json!({"type": "string"}), "Package files with more packages and modules, relative to the config. Glob patterns are allowed.")),
+        ("packages", array(reference("package"), "Nested packages.")),
+        ("modules", array(reference("module"), "Modules of the package.")),
+    ])
+}
+
+fn package_file() -> Value {
+    object("A file included by a package, with a part of its packages and modules.", &["version"], vec![
+        ("version", version()),
+        ("packages", array(reference("package"), "Packages of the including package.")),
+        ("modules", array(reference("module"), "Modules of the including package.")),
+    ])
+}
+
+fn module() -> Value {
+    object("A python module, a .py file.", &["name"], vec![
+        ("name", string("Name of the module, without .py.")),
+        ("description", optional_string("Doc string of the module.")),
+        ("imports", array(reference("import"), "Imported names, one per name.")),
+        ("exports", array(json!({"type": "string"}), "Names in __all__.")),
+        ("variables", array(reference("variable"), "Module level variables and constants.")),
+        ("functions", array(reference("function"), "Functions of the module.")),
+        ("classes", array(reference("class"), "Classes of the module.")),
+    ])
+}
+
+fn import() -> Value {
+    object("An imported name, e.g. `from os import path as p`.", &["name"], vec![
+        ("module", string("Module the name is imported from, not set for `import name`.")),
+        ("name", string("The imported name.")),
+        ("alias", string("Name after `as`.")),
+        ("level", json!({"type": "integer", "minimum": 0, "description": "Number of leading dots of a relative import."})),
+    ])
+}
+
+fn variable() -> Value {
+    object("A module level variable, e.g. `MAX_RETRIES: int = 3`.", &["name"], vec![
+        ("name", string("Name of the variable.")),
+        ("annotation", string("Type annotation, as python source.")),
+        ("value", string("Value, as python source.")),
+    ])
+}
+
+fn class() -> Value {
+    object("A python class.", &["name"], vec![
+        ("name", string("Name of the class.")),
+        ("description", optional_string("Doc string of the class.")),
+        ("parents", array(json!({"type": "string"}), "Base classes, as python source.")),
+        ("decorators", array(json!({"type": "string"}), "Decorators without the @.")),
+        ("attributes", array(reference("attribute"), "Class and instance attributes.")),
+        ("methods", array(reference("function"), "Methods of the class.")),
+        ("classes", array(reference("class"), "Classes defined in the class body.")),
+    ])
+}
+
+fn attribute() -> Value {
+    object("A class attribute, or an instance attribute assigned in __init__.", &["name"], vec![
+        ("name", string("Name of the attribute, without self.")),
+        ("annotation", string("Type annotation, as python source.")),
+        ("default", string("Value, as python source.")),
+        ("origin", json!({"enum": ["class", "instance"], "description": "Where the attribute is defined."})),
+    ])
+}
+
+fn function() -> Value {
+    object("A python function or method.", &["name"], vec![
+        ("name", string("Name of the function.")),
+        ("description", optional_string("Doc string of the function.")),
+        ("parameters", array(reference("parameter"), "Parameters of the signature.")),
+        ("returns", string("Return annotation, as python source.")),
+        ("is_async", json!({"type": "boolean", "description": "Whether the function is a coroutine."})),
+        ("decorators", array(json!({"type": "string"}), "Decorators without the @.")),
+    ])
+}
+
+fn parameter() -> Value {
+    let table = object("A parameter of a signature.", &["name"], vec![
+        ("name", string("Name of the parameter, without * or **.")),
+        ("annotation", string("Type annotation, as python source.")),
+        ("default", string("Default value, as python source.")),
+        ("kind", json!({
+            "enum": ["positional_only", "normal", "var_positional", "keyword_only", "var_keyword"],
+            "description": "Kind of the parameter, like inspect.Parameter.kind."
+        })),
+    ]);
+
+    json!({
+        "oneOf": [
+            string("A parameter as python source, e.g. `timeout: int = 30`."),
+            table
+        ]
+    })
+}
+
+/// The JSON Schema of the config file.
+/// The toml and yaml configs have the same structure, so it applies to them too.
+/// Included package files follow `#/definitions/package_file`.
+pub fn schema() -> Value {
+    let mut definitions = Map::new();
+    definitions.insert("validation".to_string(), validation());
+    definitions.insert("root".to_string(), root());
+    definitions.insert("package".to_string(), package());
+    definitions.insert("package_file".to_string(), package_file());
+    definitions.insert("module".to_string(), module());
+    definitions.insert("import".to_string(), import());
+    definitions.insert("variable".to_string(), variable());
+    definitions.insert("class".to_string(), class());
+    definitions.insert("attribute".to_string(), attribute());
+    definitions.insert("function".to_string(), function());
+    definitions.insert("parameter".to_string(), parameter());
+
+    let mut config = object("An x-ray config.", &["root"], vec![
+        ("version", version()),
+        ("root", reference("root")),
+        ("validation", reference("validation")),
+    ]);
+    config["$schema"] = json!(SCHEMA_DRAFT);
+    config["title"] = json!("x-ray config");
+    config["definitions"] = Value::Object(definitions);
+
+    config
+}
+
+/// Check a value against the parts of JSON Schema used by `schema`.
+/// Returns the path of the first value that doesn't match.
+#[cfg(test)]
+fn check_value(value: &Value, schema: &Value, root: &Value, path: &str) -> Result<(), String> {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.split('/').last().unwrap();
+        return check_value(value, &root["definitions"][name], root, path);
+    }
+
+    if let Some(schemas) = schema["oneOf"].as_array() {
+        let matches = schemas.iter().filter(|x| check_value(value, x, root, path).is_ok()).count();
+        return if matches == 1 { Ok(()) } else { Err(path.to_string()) };
+    }
+
+    if let Some(values) = schema["enum"].as_array() {
+        return if values.contains(value) { Ok(()) } else { Err(path.to_string()) };
+    }
+
+    let type_name = match *value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object"
+    };
+    let types = match schema["type"] {
+        Value::Array(ref types) => types.clone(),
+        ref type_value => vec![type_value.clone()]
+    };
+    if !types.contains(&json!(type_name)) {
+        return Err(path.to_string());
+    }
+
+    match *value {
+        Value::Array(ref items) => {
+            for (index, item) in items.iter().enumerate() {
+                check_value(item, &schema["items"], root, &format!("{}[{}]", path, index))?;
+            }
+        },
+        Value::Object(ref map) => {
+            for required in schema["required"].as_array().unwrap() {
+                if !map.contains_key(required.as_str().unwrap()) {
+                    return Err(format!("{}.{}", path, required.as_str().unwrap()));
+                }
+            }
+
+            for (key, item) in map {
+                let property = &schema["properties"][key];
+                if property.is_null() {
+                    return Err(format!("{}.{}", path, key));
+                }
+                check_value(item, property, root, &format!("{}.{}", path, key))?;
+            }
+        },
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_schema_matches_config() {
+    use serde_json;
+    use structures::{Config, PackageFile};
+
+    // Every field of the config is set.
+    let config: Config = serde_json::from_str(r#"{
+        "version": 2,
+        "root": {
+            "name": "project",
+            "modules": [{
+                "name": "api",
+                "description": null,
+                "imports": [{"module": "typing", "name": "Dict", "alias": "D", "level": 1}],
+                "exports": ["create_api"],
+                "variables": [{"name": "MAX_APIS", "annotation": "int", "value": "10"}],
+                "functions": [{
+                    "name": "create_api",
+                    "description": "Create the api.",
+                    "parameters": ["id: int", {"name": "args", "annotation": "str", "default": "None", "kind": "var_positional"}],
+                    "returns": "dict",
+                    "is_async": true,
+                    "decorators": ["cache"]
+                }],
+                "classes": [{
+                    "name": "Api",
+                    "description": null,
+                    "parents": ["Base"],
+                    "decorators": ["dataclass"],
+                    "attributes": [{"name": "id", "annotation": "int", "default": "0", "origin": "instance"}],
+                    "methods": [],
+                    "classes": [{"name": "Meta", "description": null}]
+                }]
+            }],
+            "packages": [{"name": "sample", "include": ["sample/*.toml"], "packages": [], "modules": []}]
+        },
+        "validation": {"functions": "^[a-z]+$", "constants": "^[A-Z]+$"}
+    }"#).unwrap();
+
+    let schema = schema();
+    let value = serde_json::to_value(&config).unwrap();
+    assert_eq!(check_value(&value, &schema, &schema, "config"), Ok(()));
+
+    let package_file: PackageFile = serde_json::from_str(r#"{
+        "version": 2,
+        "packages": [{"name": "nested"}],
+        "modules": [{"name": "display", "description": "The display module."}]
+    }"#).unwrap();
+    let package_value = serde_json::to_value(&package_file).unwrap();
+    assert_eq!(check_value(&package_value, &schema["definitions"]["package_file"], &schema, "package_file"), Ok(()));
+
+    // The json fixture of the integration tests.
+    let fixture = serde_json::from_str(include_str!("../tests/test_input/conf/test_gen.json")).unwrap();
+    assert_eq!(check_value(&fixture, &schema, &schema, "config"), Ok(()));
+
+    let mut typo = value.clone();
+    typo["root"]["modules"][0]["functions"][0]["return"] = json!("dict");
+    assert_eq!(check_value(&typo, &schema, &schema, "config"), Err("config.root.modules[0].functions[0].return".to_string()));
+}
@@ -1,10 +1,102 @@
 extern crate serde;
 
 use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+// version of the config schema, bumped on changes that old configs don't
+// deserialize into. Configs without a version are version 1.
+pub const CONFIG_VERSION: u64 = 2;
 
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Config {
+	pub version: u64,
+
 	pub root: Root,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub validation: Option<Validation>,
+}
+
+impl Config {
+    /// A config of the current version, without validation rules.
+    pub fn new(root: Root) -> Config {
+        Config {
+            version: CONFIG_VERSION,
+            root: root,
+            validation: None
+        }
+    }
+}
+
+// project level settings, read from the settings file.
+#[derive(Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+	#[serde(default)]
+	pub validation: Validation,
+}
+
+// naming rules used by the validations, as regexes.
+// Any rule that is not set falls back to the PEP 8 default.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Validation {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub packages: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub modules: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub classes: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub functions: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub methods: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub parameters: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub constants: Option<String>,
+}
+
+impl Validation {
+    /// Use the rules of `other` for the rules that are not set here.
+    pub fn or(self, other: Validation) -> Validation {
+        Validation {
+            packages: self.packages.or(other.packages),
+            modules: self.modules.or(other.modules),
+            classes: self.classes.or(other.classes),
+            functions: self.functions.or(other.functions),
+            methods: self.methods.or(other.methods),
+            parameters: self.parameters.or(other.parameters),
+            constants: self.constants.or(other.constants)
+        }
+    }
+
+    /// Check that every rule that is set is a valid regex.
+    pub fn check(&self) -> Result<(), String> {
+        let rules = vec![
+            ("packages", &self.packages),
+            ("modules", &self.modules),
+            ("classes", &self.classes),
+            ("functions", &self.functions),
+            ("methods", &self.methods),
+            ("parameters", &self.parameters),
+            ("constants", &self.constants),
+        ];
+
+        for (kind, rule) in rules {
+            if let Some(ref rule) = *rule {
+                if let Err(e) = Regex::new(rule) {
+                    return Err(format!("Invalid validation rule for {}: {}", kind, e));
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 // project root
@@ -24,6 +116,11 @@ pub struct Root {
 pub struct Package {
 	pub name: String,
 
+	// package files with more packages and modules of this package,
+	// relative to the config. Glob patterns like `api/*.toml` are allowed.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub include: Vec<String>,
+
     #[serde(default)]
     pub packages: Vec<Package>,
 
@@ -31,12 +128,34 @@ pub struct Package {
 	pub modules: Vec<Module>,
 }
 
+// a file included by a package, with a part of its packages and modules.
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PackageFile {
+	pub version: u64,
+
+	#[serde(default)]
+	pub packages: Vec<Package>,
+
+	#[serde(default)]
+	pub modules: Vec<Module>,
+}
+
 // python module, any python file.
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Module {
 	pub name: String,
     pub description: Option<String>,
 
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub imports: Vec<Import>,
+
+	// names in __all__.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub exports: Vec<String>,
+
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub variables: Vec<Variable>,
+
 	#[serde(default)]
 	pub functions: Vec<Function>,
 
@@ -44,6 +163,53 @@ pub struct Module {
 	pub classes: Vec<Class>,
 }
 
+// a single imported name. `from os import path, sep` is two imports.
+// module is only set for `from module import name`, level is the number
+// of leading dots of a relative import.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Import {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub module: Option<String>,
+
+	pub name: String,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub alias: Option<String>,
+
+	#[serde(default, skip_serializing_if = "is_zero")]
+	pub level: usize,
+}
+
+impl Import {
+    /// The imported name along with its alias, e.g. `numpy as np`.
+    pub fn name_source(&self) -> String {
+        match self.alias {
+            Some(ref alias) => format!("{} as {}", self.name, alias),
+            None => self.name.clone()
+        }
+    }
+
+    /// The module imported from, with the dots of a relative import.
+    /// Empty for `import name`.
+    pub fn module_source(&self) -> String {
+        match self.module {
+            Some(ref module) => ".".repeat(self.level) + module,
+            None => String::new()
+        }
+    }
+
+    pub fn to_source(&self) -> String {
+        match self.module {
+            Some(_) => format!("from {} import {}", self.module_source(), self.name_source()),
+            None => format!("import {}", self.name_source())
+        }
+    }
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Class {
 	pub name: String,
@@ -52,36 +218,452 @@ pub struct Class {
     #[serde(default)]
     pub parents: Vec<String>,
 
+	// decorators without the @, e.g. `dataclass(frozen=True)`.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub decorators: Vec<String>,
+
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub attributes: Vec<Attribute>,
+
 	#[serde(default)]
 	pub methods: Vec<Function>,
+
+	// classes defined in the class body.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub classes: Vec<Class>,
 }
 
 impl Validate for Class {
-    fn validate_case(&self) -> bool {
-        // Class name will be camel case.
-        let re = Regex::new(r"^[A-Z]{1}[a-z]+$").unwrap();
+    fn validate_case(&self, re: &Regex) -> bool {
+        re.is_match(&self.name)
+    }
+}
+
+// Where an attribute is defined, in the class body or on self in __init__.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributeOrigin {
+    Class,
+    Instance,
+}
+
+impl Default for AttributeOrigin {
+    fn default() -> AttributeOrigin {
+        AttributeOrigin::Class
+    }
+}
+
+impl AttributeOrigin {
+    pub fn is_class(&self) -> bool {
+        *self == AttributeOrigin::Class
+    }
+}
+
+// structure for a class or instance attribute.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+	pub name: String,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub annotation: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub default: Option<String>,
+
+	#[serde(default, skip_serializing_if = "AttributeOrigin::is_class")]
+	pub origin: AttributeOrigin,
+}
+
+impl Attribute {
+    /// Build an attribute from an assignment like `retries: int = 3`,
+    /// without the `self.` of instance attributes.
+    /// Returns None if the source is not a plain annotated or assigned name.
+    pub fn from_source(source: &str, origin: AttributeOrigin) -> Option<Attribute> {
+        let (name, annotation, default) = split_assignment(source)?;
+
+        Some(Attribute {
+            name: name,
+            annotation: annotation,
+            default: default,
+            origin: origin
+        })
+    }
+
+    /// The python source of the attribute, without the `self.` of instance attributes.
+    pub fn to_source(&self) -> String {
+        assignment_source(&self.name, &self.annotation, &self.default)
+    }
+}
+
+// structure for a module level variable or constant.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Variable {
+	pub name: String,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub annotation: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub value: Option<String>,
+}
+
+impl Variable {
+    /// Build a variable from an assignment like `MAX_RETRIES: int = 3`.
+    /// Returns None if the source is not a plain annotated or assigned name.
+    pub fn from_source(source: &str) -> Option<Variable> {
+        let (name, annotation, value) = split_assignment(source)?;
+
+        Some(Variable {
+            name: name,
+            annotation: annotation,
+            value: value
+        })
+    }
+
+    pub fn to_source(&self) -> String {
+        assignment_source(&self.name, &self.annotation, &self.value)
+    }
+}
+
+impl Validate for Variable {
+    fn validate_case(&self, re: &Regex) -> bool {
         re.is_match(&self.name)
     }
 }
 
+/// Split an annotated or assigned name, e.g. `retries: int = 3`, into the
+/// name, the annotation and the value.
+/// Returns None for anything else, like `x += 1`, `a, b = b, a` or a value
+/// that continues on the next line.
+fn split_assignment(source: &str) -> Option<(String, Option<String>, Option<String>)> {
+    let source = source.trim();
+
+    let (source, value) = match find_assignment(source) {
+        Some(index) => (&source[..index], Some(source[index + 1..].trim().to_string())),
+        None => (source, None)
+    };
+
+    let (name, annotation) = match source.find(':') {
+        Some(index) => (source[..index].trim(), Some(source[index + 1..].trim().to_string())),
+        None => (source.trim(), None)
+    };
+
+    let is_name = name.chars().next().map_or(false, |x| x.is_alphabetic() || x == '_') &&
+        name.chars().all(|x| x.is_alphanumeric() || x == '_');
+
+    let annotation = annotation.and_then(|x| if x.is_empty() { None } else { Some(x) });
+    let is_complete = |part: &Option<String>| part.as_ref().map_or(true, |x| is_balanced(x));
+
+    if !is_name || (annotation.is_none() && value.is_none()) || !is_complete(&value) || !is_complete(&annotation) {
+        return None;
+    }
+
+    Some((name.to_string(), annotation, value))
+}
+
+fn assignment_source(name: &str, annotation: &Option<String>, value: &Option<String>) -> String {
+    let mut source = name.to_string();
+
+    if let Some(ref annotation) = *annotation {
+        source += ": ";
+        source += annotation;
+    }
+
+    if let Some(ref value) = *value {
+        source += " = ";
+        source += value;
+    }
+
+    source
+}
+
+/// Walk the source of a statement, calling `visit` with the index, the byte
+/// and the bracket depth before it for every byte outside of strings and
+/// comments. Returns the bracket depth at the end and whether every string
+/// is closed.
+fn scan_code<F: FnMut(usize, u8, i32)>(source: &str, mut visit: F) -> (i32, bool) {
+    let source = source.as_bytes();
+    let mut depth = 0;
+    let mut quote: Option<&[u8]> = None;
+    let mut idx = 0;
+
+    while idx < source.len() {
+        let rest = &source[idx..];
+
+        if let Some(delimiter) = quote {
+            if rest.starts_with(delimiter) {
+                quote = None;
+                idx += delimiter.len();
+            } else {
+                idx += if rest[0] == b'\\' { 2 } else { 1 };
+            }
+            continue;
+        }
+
+        match rest[0] {
+            b'#' => {
+                idx += rest.iter().position(|x| *x == b'\n').unwrap_or(rest.len());
+                continue;
+            },
+            b'"' | b'\'' => {
+                let delimiter = if rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''") {
+                    &rest[..3]
+                } else {
+                    &rest[..1]
+                };
+                quote = Some(delimiter);
+                idx += delimiter.len();
+                continue;
+            },
+            chr => {
+                visit(idx, chr, depth);
+                match chr {
+                    b'(' | b'[' | b'{' => depth += 1,
+                    b')' | b']' | b'}' => depth -= 1,
+                    _ => {}
+                }
+            }
+        }
+
+        idx += 1;
+    }
+
+    (depth, quote.is_none())
+}
+
+/// Check that every bracket opened in the source is closed again, and every
+/// string is closed. Brackets in strings and comments are skipped.
+pub fn is_balanced(source: &str) -> bool {
+    let (depth, is_closed) = scan_code(source, |_, _, _| {});
+
+    depth == 0 && is_closed
+}
+
+/// Join the dotted path of a parent with the name of a child.
+pub fn dotted_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", parent, name)
+    }
+}
+
 // structure for a forming python function.
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Function {
     pub name: String,
     pub description: Option<String>,
 
 	#[serde(default)]
-	pub parameters: Vec<String>,
+	pub parameters: Vec<Parameter>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub returns: Option<String>,
+
+	#[serde(default, skip_serializing_if = "is_false")]
+	pub is_async: bool,
+
+	// decorators without the @, e.g. `app.route("/x")`.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub decorators: Vec<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl Validate for Function {
-    fn validate_case(&self) -> bool {
-        // function name will be snake case.
-        let re = Regex::new(r"^[a-z_]+$").unwrap();
+    fn validate_case(&self, re: &Regex) -> bool {
+        re.is_match(&self.name)
+    }
+}
+
+// The kind of a parameter, same as python's inspect.Parameter.kind.
+//
+// The `/` and `*` markers of a signature are not stored, they are
+// implied by the kinds of the parameters around them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterKind {
+    PositionalOnly,
+    Normal,
+    VarPositional,
+    KeywordOnly,
+    VarKeyword,
+}
+
+impl Default for ParameterKind {
+    fn default() -> ParameterKind {
+        ParameterKind::Normal
+    }
+}
+
+impl ParameterKind {
+    pub fn is_normal(&self) -> bool {
+        *self == ParameterKind::Normal
+    }
+}
+
+// structure for a function parameter.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Parameter {
+	pub name: String,
+
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub annotation: Option<String>,
+
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub default: Option<String>,
+
+	#[serde(skip_serializing_if = "ParameterKind::is_normal")]
+	pub kind: ParameterKind,
+}
+
+impl Parameter {
+    pub fn new(name: &str) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            annotation: None,
+            default: None,
+            kind: ParameterKind::Normal
+        }
+    }
+
+    /// Build a parameter from its python source, e.g. `timeout: int = 30`,
+    /// `*args` or `**kwargs`.
+    pub fn from_source(source: &str) -> Parameter {
+        let source = source.trim();
+
+        let (kind, source) = if source.starts_with("**") {
+            (ParameterKind::VarKeyword, &source[2..])
+        } else if source.starts_with('*') {
+            (ParameterKind::VarPositional, &source[1..])
+        } else {
+            (ParameterKind::Normal, source)
+        };
+
+        let (source, default) = match find_assignment(source) {
+            Some(index) => (&source[..index], Some(source[index + 1..].trim().to_string())),
+            None => (source, None)
+        };
+
+        let (name, annotation) = match source.find(':') {
+            Some(index) => (&source[..index], Some(source[index + 1..].trim().to_string())),
+            None => (source, None)
+        };
+
+        Parameter {
+            name: name.trim().to_string(),
+            annotation: annotation,
+            default: default,
+            kind: kind
+        }
+    }
+
+    /// The python source of the parameter, without the `/` and `*` markers.
+    pub fn to_source(&self) -> String {
+        let mut source = match self.kind {
+            ParameterKind::VarPositional => "*".to_string(),
+            ParameterKind::VarKeyword => "**".to_string(),
+            _ => String::new()
+        };
+
+        source += &self.name;
+
+        if let Some(ref annotation) = self.annotation {
+            source += ": ";
+            source += annotation;
+        }
+
+        if let Some(ref default) = self.default {
+            // PEP 8: no spaces around = unless the parameter is annotated.
+            if self.annotation.is_some() {
+                source += " = ";
+            } else {
+                source += "=";
+            }
+            source += default;
+        }
+
+        source
+    }
+}
+
+/// Find the `=` separating a parameter from its default value.
+/// Comparison operators like `==` and `<=` are skipped, and so is every `=`
+/// in brackets or strings, like the one in `Annotated[str, Query(max_length=50)]`.
+fn find_assignment(source: &str) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut result = None;
+
+    scan_code(source, |index, chr, depth| {
+        if result.is_some() || chr != b'=' || depth != 0 {
+            return;
+        }
+
+        let prev = if index > 0 { bytes[index - 1] } else { b' ' };
+        let next = if index + 1 < bytes.len() { bytes[index + 1] } else { b' ' };
+
+        if next != b'=' && !b"=<>!".contains(&prev) {
+            result = Some(index);
+        }
+    });
+
+    result
+}
+
+// Parameters are tables in the config. A plain string like "timeout=30"
+// is accepted too, which is how parameters used to be written.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParameterDef {
+    Source(String),
+    Table {
+        name: String,
+        #[serde(default)]
+        annotation: Option<String>,
+        #[serde(default)]
+        default: Option<String>,
+        #[serde(default)]
+        kind: ParameterKind,
+    },
+}
+
+impl<'de> Deserialize<'de> for Parameter {
+    fn deserialize<D>(deserializer: D) -> Result<Parameter, D::Error> where D: Deserializer<'de> {
+        let parameter = match ParameterDef::deserialize(deserializer)? {
+            ParameterDef::Source(source) => Parameter::from_source(&source),
+            ParameterDef::Table {name, annotation, default, kind} => Parameter {
+                name: name,
+                annotation: annotation,
+                default: default,
+                kind: kind
+            }
+        };
+
+        Ok(parameter)
+    }
+}
+
+impl Validate for Package {
+    fn validate_case(&self, re: &Regex) -> bool {
+        re.is_match(&self.name)
+    }
+}
+
+impl Validate for Module {
+    fn validate_case(&self, re: &Regex) -> bool {
+        re.is_match(&self.name)
+    }
+}
+
+impl Validate for Parameter {
+    fn validate_case(&self, re: &Regex) -> bool {
         re.is_match(&self.name)
     }
 }
 
 pub trait Validate {
-    fn validate_case(&self) -> bool;
+    /// Check the name against the naming rule.
+    fn validate_case(&self, re: &Regex) -> bool;
 }
@@ -2,7 +2,7 @@ extern crate rustache;
 
 use std::io::Cursor;
 use self::rustache::{HashBuilder, Render};
-use structures::{Class, Function};
+use structures::{Attribute, AttributeOrigin, Class, Function, Import, Parameter, ParameterKind, Variable};
 
 
 pub fn module_desc_template(description: String) -> String {
@@ -24,6 +24,131 @@ pub fn module_desc_template(description: String) -> String {
     String::from_utf8(out.into_inner()).unwrap()
 }
 
+/// The import statements of a module.
+///
+/// `__future__` imports come first, they have to be at the top of the file.
+/// Then absolute imports, then relative ones, each group separated by an empty
+/// line. `import x` statements come before `from x import y` statements,
+/// and names imported from the same module are joined into one statement.
+/// Everything is sorted, so the output doesn't depend on the order of the
+/// imports in the config.
+pub fn imports_template(imports: &[Import]) -> String {
+    let mut future: Vec<String> = Vec::new();
+    let mut absolute: Vec<String> = Vec::new();
+    let mut relative: Vec<String> = Vec::new();
+
+    let mut plain_imports: Vec<&Import> = imports.iter().filter(|x| x.module.is_none()).collect();
+    plain_imports.sort_by_key(|x| x.name_source());
+    plain_imports.dedup_by_key(|x| x.name_source());
+    for import in plain_imports {
+        absolute.push(import.to_source());
+    }
+
+    let mut from_imports: Vec<&Import> = imports.iter().filter(|x| x.module.is_some()).collect();
+    from_imports.sort_by_key(|x| (x.level, x.module_source(), x.name_source()));
+    from_imports.dedup_by_key(|x| (x.level, x.module_source(), x.name_source()));
+
+    let mut index = 0;
+    while index < from_imports.len() {
+        let module = from_imports[index].module_source();
+        let names: Vec<String> = from_imports[index..].iter()
+            .take_while(|x| x.module_source() == module)
+            .map(|x| x.name_source())
+            .collect();
+        index += names.len();
+
+        let mut statement = format!("from {} import {}", module, names.join(", "));
+        // PEP 8: lines up to 79 characters.
+        if statement.len() > 79 {
+            statement = format!("from {} import (\n    {},\n)", module, names.join(",\n    "));
+        }
+
+        if module == "__future__" {
+            future.push(statement);
+        } else if module.starts_with('.') {
+            relative.push(statement);
+        } else {
+            absolute.push(statement);
+        }
+    }
+
+    let mut content = String::new();
+    for group in &[future, absolute, relative] {
+        if !group.is_empty() {
+            content += "\n";
+            content += &group.join("\n");
+            content += "\n";
+        }
+    }
+
+    content
+}
+
+/// The `__all__` of a module.
+pub fn exports_template(exports: &[String]) -> String {
+    let names = exports.iter().map(|x| format!("\"{}\"", x)).collect::<Vec<_>>();
+
+    format!("\n__all__ = [{}]\n", names.join(", "))
+}
+
+/// The module level variables, one assignment per line.
+pub fn variables_template(variables: &[Variable]) -> String {
+    let mut content = String::from("\n");
+
+    for variable in variables {
+        content += &variable.to_source();
+        content += "\n";
+    }
+
+    content
+}
+
+/// Join the parameters of a signature, adding the `/` and `*` markers
+/// where the parameter kinds need them.
+fn parameters_source(parameters: &[Parameter]) -> String {
+    let mut sources: Vec<String> = Vec::new();
+    let mut has_var_positional = false;
+
+    for (index, parameter) in parameters.iter().enumerate() {
+        match parameter.kind {
+            ParameterKind::VarPositional => has_var_positional = true,
+            ParameterKind::KeywordOnly if !has_var_positional => {
+                sources.push("*".to_string());
+                has_var_positional = true;
+            },
+            _ => {}
+        }
+
+        sources.push(parameter.to_source());
+
+        let next_kind = parameters.get(index + 1).map(|next| next.kind);
+        if parameter.kind == ParameterKind::PositionalOnly && next_kind != Some(ParameterKind::PositionalOnly) {
+            sources.push("/".to_string());
+        }
+    }
+
+    sources.join(", ")
+}
+
+/// The decorator lines of a function or class. Each line is followed by
+/// `indent`, the indentation of the line that comes after it.
+fn decorators_source(decorators: &[String], indent: &str) -> String {
+    decorators.iter().map(|x| format!("@{}\n{}", x, indent)).collect()
+}
+
+/// The signature of a function as written after its name,
+/// e.g. `(self, timeout: int = 30) -> bool`.
+pub fn signature(function: &Function) -> String {
+    let mut signature = format!("({})", parameters_source(&function.parameters));
+
+    if let Some(ref returns) = function.returns {
+        signature += " -> ";
+        signature += returns;
+    }
+
+    signature
+}
+
 pub fn function_template(function: Function) -> String {
     let mut func_desc_bool = false;
     let func_desc = match function.description {
@@ -35,27 +160,25 @@ pub fn function_template(function: Function) -> String {
     };
 
     let function_template = r#"
-def {{func_name}}({{parameters}}):
+{{&decorators}}{{#is_async}}async {{/is_async}}def {{func_name}}({{&parameters}}){{#returns_bool}} -> {{&returns}}{{/returns_bool}}:
     {{#func_desc_bool}}"""
     {{func_desc}}
     """
     {{/func_desc_bool}}pass
 "#;
 
-    let mut function_parameters = String::new();
-    let mut i:i32 = 0;
+    let function_parameters = parameters_source(&function.parameters);
 
-    for parameter in function.parameters {
-        if i != 0 {
-            function_parameters += ", "
-        }
-        function_parameters += &parameter;
-        i += 1;
-    }
+    let returns_bool = function.returns.is_some();
+    let returns = function.returns.unwrap_or_default();
 
     let mut data = HashBuilder::new();
     data = data.insert("parameters", function_parameters);
     data = data.insert("func_name", function.name);
+    data = data.insert("returns", returns);
+    data = data.insert("returns_bool", returns_bool);
+    data = data.insert("is_async", function.is_async);
+    data = data.insert("decorators", decorators_source(&function.decorators, ""));
 
     data = data.insert("func_desc", func_desc);
     data = data.insert("func_desc_bool", func_desc_bool);
@@ -70,23 +193,19 @@ def {{func_name}}({{parameters}}):
 }
 
 pub fn method_template(method: Function) -> String {
+    method_source(method, "pass")
+}
+
+/// Render a method with the given body in place of `pass`.
+fn method_source(method: Function, body: &str) -> String {
     let method_template = r#"
-    def {{ method_name }}({{method_parameters}}):
+    {{&method_decorators}}{{#method_async}}async {{/method_async}}def {{ method_name }}({{&method_parameters}}){{#method_returns_bool}} -> {{&method_returns}}{{/method_returns_bool}}:
         {{#method_desc_bool}}"""
         {{ method_desc }}
         """
-        {{/method_desc_bool}}pass
+        {{/method_desc_bool}}{{&method_body}}
 "#;
-    let mut method_parameters = String::new();
-    let mut i:i32 = 0;
-
-    for parameter in method.parameters {
-        if i != 0 {
-            method_parameters += ", "
-        }
-        method_parameters += &parameter;
-        i += 1;
-    }
+    let method_parameters = parameters_source(&method.parameters);
 
     let mut method_desc_bool = false;
 
@@ -98,11 +217,19 @@ pub fn method_template(method: Function) -> String {
         None => String::new(),
     };
 
+    let method_returns_bool = method.returns.is_some();
+    let method_returns = method.returns.unwrap_or_default();
+
     let mut method_data = HashBuilder::new();
     method_data = method_data.insert("method_parameters", method_parameters);
+    method_data = method_data.insert("method_returns", method_returns);
+    method_data = method_data.insert("method_returns_bool", method_returns_bool);
+    method_data = method_data.insert("method_async", method.is_async);
+    method_data = method_data.insert("method_decorators", decorators_source(&method.decorators, "    "));
     method_data = method_data.insert("method_name", method.name);
     method_data = method_data.insert("method_desc_bool", method_desc_bool);
     method_data = method_data.insert("method_desc", method_desc);
+    method_data = method_data.insert("method_body", body);
 
     let mut method_out = Cursor::new(Vec::new());
     match method_data.render(&method_template, &mut method_out) {
@@ -137,18 +264,43 @@ pub fn class_template(class: Class) -> String {
         class_inheritance += val
     }
 
+    let mut nested_classes_string = String::new();
+    for nested_class in class.classes {
+        nested_classes_string += &indent(&class_template(nested_class));
+    }
+
     let class_template = r#"
-class {{ class_name }}{{#class_inheritance_bool}}({{class_inheritance}}){{/class_inheritance_bool}}:
+{{&class_decorators}}class {{ class_name }}{{#class_inheritance_bool}}({{&class_inheritance}}){{/class_inheritance_bool}}:
     {{#class_desc_bool}}"""
     {{ class_desc }}
-    """{{/class_desc_bool}}
+    """{{/class_desc_bool}}{{#class_empty}}pass{{/class_empty}}
 "#;
 
-    let mut method_template_string = String::new();
-    let methods = class.methods;
+    let (class_attributes, instance_attributes): (Vec<Attribute>, Vec<Attribute>) = class.attributes.into_iter()
+        .partition(|x| x.origin == AttributeOrigin::Class);
+
+    let mut attributes_string = String::new();
+    if !class_attributes.is_empty() {
+        attributes_string += "\n";
+        for attribute in class_attributes {
+            attributes_string += &format!("    {}\n", attribute.to_source());
+        }
+    }
+
+    // Instance attributes are assigned in __init__, which is added when
+    // the class doesn't have one.
+    let mut methods = class.methods;
+    if !instance_attributes.is_empty() && !methods.iter().any(|x| x.name == "__init__") {
+        methods.insert(0, init_method());
+    }
 
+    let mut method_template_string = String::new();
     for method in methods {
-        method_template_string += &method_template(method);
+        if method.name == "__init__" && !instance_attributes.is_empty() {
+            method_template_string += &init_template(method, &instance_attributes);
+        } else {
+            method_template_string += &method_template(method);
+        }
     }
 
     let mut data = HashBuilder::new();
@@ -157,6 +309,9 @@ class {{ class_name }}{{#class_inheritance_bool}}({{class_inheritance}}){{/class
     data = data.insert("class_desc_bool", class_desc_bool);
     data = data.insert("class_inheritance", class_inheritance);
     data = data.insert("class_inheritance_bool", class_inheritance_bool);
+    data = data.insert("class_decorators", decorators_source(&class.decorators, ""));
+    data = data.insert("class_empty", !class_desc_bool && attributes_string.is_empty()
+        && nested_classes_string.is_empty() && method_template_string.is_empty());
 
     let mut out = Cursor::new(Vec::new());
     match data.render(class_template, &mut out) {
@@ -165,7 +320,167 @@ class {{ class_name }}{{#class_inheritance_bool}}({{class_inheritance}}){{/class
     };
 
     // return the filled class template
-    String::from_utf8(out.into_inner()).unwrap() + &method_template_string
+    String::from_utf8(out.into_inner()).unwrap() + &attributes_string + &nested_classes_string + &method_template_string
+}
+
+/// A plain `__init__(self)`, for classes with instance attributes and
+/// without an `__init__` of their own.
+pub fn init_method() -> Function {
+    Function {
+        name: "__init__".to_string(),
+        description: None,
+        parameters: vec![Parameter::new("self")],
+        returns: None,
+        is_async: false,
+        decorators: Vec::new()
+    }
+}
+
+/// The `__init__` method of a class, assigning the instance attributes.
+pub fn init_template(method: Function, attributes: &[Attribute]) -> String {
+    let body = attributes.iter()
+        .map(|x| format!("self.{}", x.to_source()))
+        .collect::<Vec<_>>()
+        .join("\n        ");
+
+    method_source(method, &body)
+}
+
+/// Indent every line that is not empty by one level.
+pub fn indent(source: &str) -> String {
+    source.split('\n')
+        .map(|line| if line.is_empty() { line.to_string() } else { format!("    {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The name of the test for a function or method, e.g. `test_get_animal`.
+/// Leading and trailing underscores are dropped, `__init__` gives `test_init`.
+fn pytest_name(name: &str) -> String {
+    format!("test_{}", name.trim_matches('_'))
+}
+
+/// A pytest test for a function, with `pass` as body.
+pub fn pytest_function_template(function: &Function) -> String {
+    function_template(Function {
+        name: pytest_name(&function.name),
+        description: None,
+        parameters: Vec::new(),
+        returns: None,
+        is_async: false,
+        decorators: Vec::new()
+    })
+}
+
+/// A pytest test class for a class, with one test per method. Nested
+/// classes get test classes of their own, named after the outer class.
+pub fn pytest_class_template(class: &Class, prefix: &str) -> String {
+    let class_name = format!("{}{}", prefix, class.name);
+
+    let mut methods: Vec<Function> = Vec::new();
+    for method in &class.methods {
+        let name = pytest_name(&method.name);
+        // Overloads and e.g. `_load` and `load` share a test.
+        if !methods.iter().any(|x| x.name == name) {
+            methods.push(Function {
+                name: name,
+                description: None,
+                parameters: vec![Parameter::new("self")],
+                returns: None,
+                is_async: false,
+                decorators: Vec::new()
+            });
+        }
+    }
+
+    let mut content = class_template(Class {
+        name: format!("Test{}", class_name),
+        description: Some(format!("Tests for {}.", class_name)),
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        attributes: Vec::new(),
+        methods: methods,
+        classes: Vec::new()
+    });
+
+    for nested_class in &class.classes {
+        content += &pytest_class_template(nested_class, &class_name);
+    }
+
+    content
+}
+
+/// An annotated name in a stub, the value is left out.
+/// Without an annotation the value is `...`, which type checkers read as Any.
+fn stub_assignment(name: &str, annotation: &Option<String>) -> String {
+    match *annotation {
+        Some(ref annotation) => format!("{}: {}", name, annotation),
+        None => format!("{} = ...", name)
+    }
+}
+
+/// The module level variables of a stub.
+pub fn variables_stub(variables: &[Variable]) -> String {
+    let mut content = String::from("\n");
+
+    for variable in variables {
+        content += &stub_assignment(&variable.name, &variable.annotation);
+        content += "\n";
+    }
+
+    content
+}
+
+/// A function or method in a stub, with `...` as body and in place of
+/// the default values.
+fn function_stub_source(function: &Function, indent: &str) -> String {
+    let mut stub = function.clone();
+    for parameter in &mut stub.parameters {
+        if parameter.default.is_some() {
+            parameter.default = Some("...".to_string());
+        }
+    }
+
+    let is_async = if function.is_async { "async " } else { "" };
+
+    format!("{}{}{}def {}{}: ...\n", indent, decorators_source(&function.decorators, indent), is_async,
+            function.name, signature(&stub))
+}
+
+pub fn function_stub(function: &Function) -> String {
+    format!("\n{}", function_stub_source(function, ""))
+}
+
+/// A class in a stub. Class and instance attributes are both declared in
+/// the class body, a class without any members gets `...` as body.
+pub fn class_stub(class: &Class) -> String {
+    let mut body = String::new();
+
+    for attribute in &class.attributes {
+        body += &format!("    {}\n", stub_assignment(&attribute.name, &attribute.annotation));
+    }
+
+    for method in &class.methods {
+        body += &function_stub_source(method, "    ");
+    }
+
+    // Nested classes come last, each one after an empty line.
+    for nested_class in &class.classes {
+        body += &indent(&class_stub(nested_class));
+    }
+
+    let parents = if class.parents.is_empty() {
+        String::new()
+    } else {
+        format!("({})", class.parents.join(", "))
+    };
+
+    let header = format!("\n{}class {}{}:", decorators_source(&class.decorators, ""), class.name, parents);
+    if body.is_empty() {
+        header + " ...\n"
+    } else {
+        header + "\n" + &body
+    }
 }
 
 // Unit tests.
@@ -174,7 +489,10 @@ fn test_function_template() {
     let function = Function {
         name: "display".to_string(),
         description: Some("This is the display function.".to_string()),
-        parameters: vec!["self".to_string(), "params".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self"), Parameter::new("params")]
     };
 
     let function_template_content = function_template(function);
@@ -194,7 +512,10 @@ fn test_function_template_without_doc_string() {
     let function = Function {
         name: "display".to_string(),
         description: None,
-        parameters: vec!["self".to_string(), "params".to_string()]
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self"), Parameter::new("params")]
     };
 
     let function_template_content = function_template(function);
@@ -212,6 +533,9 @@ fn test_class_template() {
         name: "Animal".to_string(),
         description: Some("This is the animal class.".to_string()),
         parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
         methods: Vec::new()
     };
 
@@ -232,6 +556,9 @@ fn test_class_template_with_inheritance() {
         name: "Animal".to_string(),
         description: Some("This is the animal class.".to_string()),
         parents: vec!("Object".to_string()),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
         methods: Vec::new()
     };
 
@@ -253,6 +580,9 @@ fn test_class_template_with_methods() {
     let function = Function {
         name: "display".to_string(),
         description: Some("This is the display function.".to_string()),
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
         parameters: Vec::new()
     };
 
@@ -262,6 +592,9 @@ fn test_class_template_with_methods() {
         name: "Animal".to_string(),
         description: Some("This is the animal class.".to_string()),
         parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
         methods: methods
     };
 
@@ -281,4 +614,511 @@ class Animal:
 "#;
 
     assert_eq!(class_template_content, expected_class_template_content);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_function_template_with_parameter_kinds() {
+    let function = Function {
+        name: "display".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![
+            Parameter {
+                name: "msg".to_string(),
+                annotation: Some("str".to_string()),
+                default: None,
+                kind: ParameterKind::PositionalOnly
+            },
+            Parameter::from_source("level=1"),
+            Parameter {
+                name: "timeout".to_string(),
+                annotation: Some("int".to_string()),
+                default: Some("30".to_string()),
+                kind: ParameterKind::KeywordOnly
+            },
+            Parameter::from_source("**kwargs")
+        ]
+    };
+
+    let function_template_content = function_template(function);
+    let expected_function_template_content = r#"
+def display(msg: str, /, level=1, *, timeout: int = 30, **kwargs):
+    pass
+"#;
+
+    assert_eq!(function_template_content, expected_function_template_content);
+}
+
+#[test]
+fn test_class_template_with_method_return_annotation() {
+    let method = Function {
+        name: "get_name".to_string(),
+        description: None,
+        returns: Some("str".to_string()),
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
+    };
+
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
+        methods: vec![method]
+    };
+
+    let class_template_content = class_template(class);
+    let expected_class_template_content = r#"
+class Animal:
+    """
+    This is the animal class.
+    """
+
+    def get_name(self) -> str:
+        pass
+"#;
+
+    assert_eq!(class_template_content, expected_class_template_content);
+}
+
+#[test]
+fn test_function_template_async() {
+    let function = Function {
+        name: "handler".to_string(),
+        description: None,
+        returns: None,
+        is_async: true,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("request")]
+    };
+
+    let function_template_content = function_template(function);
+    let expected_function_template_content = r#"
+async def handler(request):
+    pass
+"#;
+
+    assert_eq!(function_template_content, expected_function_template_content);
+}
+
+#[test]
+fn test_class_template_with_async_method() {
+    let method = Function {
+        name: "fetch".to_string(),
+        description: None,
+        returns: None,
+        is_async: true,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
+    };
+
+    let class = Class {
+        name: "Client".to_string(),
+        description: Some("HTTP client.".to_string()),
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
+        methods: vec![method]
+    };
+
+    let class_template_content = class_template(class);
+    let expected_class_template_content = r#"
+class Client:
+    """
+    HTTP client.
+    """
+
+    async def fetch(self):
+        pass
+"#;
+
+    assert_eq!(class_template_content, expected_class_template_content);
+}
+
+#[test]
+fn test_function_template_with_decorators() {
+    let function = Function {
+        name: "index".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: vec!["app.route(\"/x\", methods=[\"GET\"])".to_string()],
+        parameters: vec![Parameter::from_source("page=\"home\"")]
+    };
+
+    let function_template_content = function_template(function);
+    let expected_function_template_content = r#"
+@app.route("/x", methods=["GET"])
+def index(page="home"):
+    pass
+"#;
+
+    assert_eq!(function_template_content, expected_function_template_content);
+}
+
+#[test]
+fn test_class_template_with_decorators() {
+    let method = Function {
+        name: "name".to_string(),
+        description: None,
+        returns: Some("str".to_string()),
+        is_async: false,
+        decorators: vec!["property".to_string(), "abstractmethod".to_string()],
+        parameters: vec![Parameter::new("self")]
+    };
+
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: Vec::new(),
+        decorators: vec!["dataclass(frozen=True)".to_string()],
+        classes: Vec::new(),
+        attributes: Vec::new(),
+        methods: vec![method]
+    };
+
+    let class_template_content = class_template(class);
+    let expected_class_template_content = r#"
+@dataclass(frozen=True)
+class Animal:
+    """
+    This is the animal class.
+    """
+
+    @property
+    @abstractmethod
+    def name(self) -> str:
+        pass
+"#;
+
+    assert_eq!(class_template_content, expected_class_template_content);
+}
+
+#[test]
+fn test_class_template_with_attributes() {
+    let method = Function {
+        name: "__init__".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self"), Parameter::new("name")]
+    };
+
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: vec![
+            Attribute::from_source("legs: int = 4", AttributeOrigin::Class).unwrap(),
+            Attribute::from_source("name = name", AttributeOrigin::Instance).unwrap(),
+            Attribute::from_source("owner: Optional[str]", AttributeOrigin::Instance).unwrap()
+        ],
+        methods: vec![method]
+    };
+
+    let class_template_content = class_template(class);
+    let expected_class_template_content = r#"
+class Animal:
+    """
+    This is the animal class.
+    """
+
+    legs: int = 4
+
+    def __init__(self, name):
+        self.name = name
+        self.owner: Optional[str]
+"#;
+
+    assert_eq!(class_template_content, expected_class_template_content);
+}
+
+#[test]
+fn test_class_template_adds_init_for_instance_attributes() {
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: vec![Attribute::from_source("name = None", AttributeOrigin::Instance).unwrap()],
+        methods: Vec::new()
+    };
+
+    let class_template_content = class_template(class);
+    let expected_class_template_content = r#"
+class Animal:
+    """
+    This is the animal class.
+    """
+
+    def __init__(self):
+        self.name = None
+"#;
+
+    assert_eq!(class_template_content, expected_class_template_content);
+}
+
+#[test]
+fn test_exports_and_variables_template() {
+    let exports = vec!["connect".to_string(), "MAX_RETRIES".to_string()];
+    let variables = vec![
+        Variable::from_source("MAX_RETRIES: int = 3").unwrap(),
+        Variable::from_source("logger: Logger").unwrap()
+    ];
+
+    assert_eq!(exports_template(&exports), "\n__all__ = [\"connect\", \"MAX_RETRIES\"]\n");
+    assert_eq!(variables_template(&variables), "\nMAX_RETRIES: int = 3\nlogger: Logger\n");
+}
+
+#[test]
+fn test_imports_template() {
+    let imports = vec![
+        Import {module: Some("".to_string()), name: "utils".to_string(), alias: None, level: 1},
+        Import {module: Some("typing".to_string()), name: "Optional".to_string(), alias: None, level: 0},
+        Import {module: None, name: "sys".to_string(), alias: None, level: 0},
+        Import {module: Some("models".to_string()), name: "Animal".to_string(), alias: None, level: 2},
+        Import {module: Some("typing".to_string()), name: "Dict".to_string(), alias: None, level: 0},
+        Import {module: None, name: "numpy".to_string(), alias: Some("np".to_string()), level: 0},
+        Import {module: Some("typing".to_string()), name: "Dict".to_string(), alias: None, level: 0},
+        Import {module: Some("collections".to_string()), name: "OrderedDict".to_string(), alias: Some("odict".to_string()), level: 0},
+    ];
+
+    assert_eq!(imports_template(&imports), r#"
+import numpy as np
+import sys
+from collections import OrderedDict as odict
+from typing import Dict, Optional
+
+from . import utils
+from ..models import Animal
+"#);
+
+    let names = vec!["Any", "Callable", "Dict", "Iterable", "List", "Optional", "Sequence", "Tuple", "Union"];
+    let imports = names.iter()
+        .map(|x| Import {module: Some("typing".to_string()), name: x.to_string(), alias: None, level: 0})
+        .collect::<Vec<_>>();
+
+    assert_eq!(imports_template(&imports), r#"
+from typing import (
+    Any,
+    Callable,
+    Dict,
+    Iterable,
+    List,
+    Optional,
+    Sequence,
+    Tuple,
+    Union,
+)
+"#);
+}
+
+#[test]
+fn test_imports_template_future_first() {
+    let imports = vec![
+        Import {module: None, name: "os".to_string(), alias: None, level: 0},
+        Import {module: Some("__future__".to_string()), name: "annotations".to_string(), alias: None, level: 0},
+    ];
+
+    assert_eq!(imports_template(&imports), r#"
+from __future__ import annotations
+
+import os
+"#);
+}
+
+#[test]
+fn test_class_template_with_nested_classes() {
+    let meta = Class {
+        name: "Meta".to_string(),
+        description: Some("Options of the model.".to_string()),
+        parents: Vec::new(),
+        classes: Vec::new(),
+        decorators: Vec::new(),
+        attributes: vec![Attribute::from_source("ordering = [\"name\"]", AttributeOrigin::Class).unwrap()],
+        methods: vec![Function {
+            name: "get_ordering".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: vec!["classmethod".to_string()],
+            parameters: vec![Parameter::new("cls")]
+        }]
+    };
+
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: vec!["Model".to_string()],
+        classes: vec![meta],
+        decorators: Vec::new(),
+        attributes: vec![Attribute::from_source("name = CharField()", AttributeOrigin::Class).unwrap()],
+        methods: Vec::new()
+    };
+
+    let class_template_content = class_template(class);
+    let expected_class_template_content = r#"
+class Animal(Model):
+    """
+    This is the animal class.
+    """
+
+    name = CharField()
+
+    class Meta:
+        """
+        Options of the model.
+        """
+
+        ordering = ["name"]
+
+        @classmethod
+        def get_ordering(cls):
+            pass
+"#;
+
+    assert_eq!(class_template_content, expected_class_template_content);
+}
+
+#[test]
+fn test_function_stub() {
+    let function = Function {
+        name: "fetch".to_string(),
+        description: Some("Fetch the url.".to_string()),
+        returns: Some("bytes".to_string()),
+        is_async: true,
+        decorators: vec!["overload".to_string()],
+        parameters: vec![
+            Parameter::from_source("url: str"),
+            Parameter::from_source("timeout: int = 30"),
+            Parameter::from_source("retry=True"),
+        ]
+    };
+
+    let expected_function_stub = r#"
+@overload
+async def fetch(url: str, timeout: int = ..., retry=...) -> bytes: ...
+"#;
+
+    assert_eq!(function_stub(&function), expected_function_stub);
+}
+
+#[test]
+fn test_class_stub() {
+    let meta = Class {
+        name: "Meta".to_string(),
+        description: Some("Options of the model.".to_string()),
+        parents: Vec::new(),
+        classes: Vec::new(),
+        decorators: Vec::new(),
+        attributes: Vec::new(),
+        methods: Vec::new()
+    };
+
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: vec!["Model".to_string()],
+        classes: vec![meta],
+        decorators: vec!["dataclass".to_string()],
+        attributes: vec![
+            Attribute::from_source("legs: int = 4", AttributeOrigin::Class).unwrap(),
+            Attribute::from_source("name = None", AttributeOrigin::Instance).unwrap(),
+        ],
+        methods: vec![Function {
+            name: "get_animal".to_string(),
+            description: None,
+            returns: Some("Animal".to_string()),
+            is_async: false,
+            decorators: vec!["property".to_string()],
+            parameters: vec![Parameter::new("self")]
+        }]
+    };
+
+    let expected_class_stub = r#"
+@dataclass
+class Animal(Model):
+    legs: int
+    name = ...
+    @property
+    def get_animal(self) -> Animal: ...
+
+    class Meta: ...
+"#;
+
+    assert_eq!(class_stub(&class), expected_class_stub);
+}
+
+#[test]
+fn test_pytest_class_template() {
+    let meta = Class {
+        name: "Meta".to_string(),
+        description: None,
+        parents: Vec::new(),
+        classes: Vec::new(),
+        decorators: Vec::new(),
+        attributes: Vec::new(),
+        methods: Vec::new()
+    };
+
+    let class = Class {
+        name: "Animal".to_string(),
+        description: Some("This is the animal class.".to_string()),
+        parents: vec!["Model".to_string()],
+        classes: vec![meta],
+        decorators: Vec::new(),
+        attributes: Vec::new(),
+        methods: vec![Function {
+            name: "__init__".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec![Parameter::new("self")]
+        }, Function {
+            name: "get_animal".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec![Parameter::new("self")]
+        }, Function {
+            name: "_get_animal".to_string(),
+            description: None,
+            returns: None,
+            is_async: false,
+            decorators: Vec::new(),
+            parameters: vec![Parameter::new("self")]
+        }]
+    };
+
+    let expected_pytest_class_template = r#"
+class TestAnimal:
+    """
+    Tests for Animal.
+    """
+
+    def test_init(self):
+        pass
+
+    def test_get_animal(self):
+        pass
+
+class TestAnimalMeta:
+    """
+    Tests for AnimalMeta.
+    """
+"#;
+
+    assert_eq!(pytest_class_template(&class, ""), expected_pytest_class_template);
+}
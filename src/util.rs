@@ -1,3 +1,4 @@
+use std::io;
 use std::io::prelude::*;
 use std::fs;
 use std::fs::File;
@@ -5,90 +6,250 @@ use std::path::Path;
 
 use toml;
 use serde_yaml;
+use serde_json;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 
-use structures::{Config, Root};
+use cli::{ConfType, STDIO};
+use error::{Error, Result};
+use migrate;
+use include;
+use structures::{Config, Root, PackageFile, Settings, CONFIG_VERSION};
 
 const FILE_EXTENSION:&'static str = ".py";
+const STUB_FILE_EXTENSION:&'static str = ".pyi";
 const INIT_FILE:&'static str = "__init__.py";
+const SETTINGS_FILE:&'static str = ".x-ray.toml";
 
-pub fn read_file(filename: &str) -> String {
-    let file = File::open(filename);
-
-    let mut file_content = String::new();
-
-    let mut file = match file {
+pub fn read_file<P: AsRef<Path>>(filename: P) -> Result<String> {
+    let filename = filename.as_ref();
+    let mut file = match File::open(filename) {
         Ok(file) => file,
-        Err(error) => panic!("The following error occurred {:?}", error),
+        Err(error) => return Err(Error::io(filename, error)),
     };
 
+    let mut file_content = String::new();
+
     match file.read_to_string(&mut file_content) {
         Ok(_) => {},
-        Err(error) => panic!("There was an error {:?} reading the file {}", error, filename),
+        Err(error) => return Err(Error::io(filename, error)),
     }
 
     // return the file content.
-    file_content
+    Ok(file_content)
 }
 
-pub fn write_to_file(path: &Path, filename: &str, content: &str) {
+pub fn write_to_file(path: &Path, filename: &str, content: &str) -> Result<()> {
 	// Write the python source to file.
 	// Args: file name, the content of the file.
 	//
 	// filename & content will be &str since we won't be manipulating it.
 	let filename = filename.to_string() + FILE_EXTENSION;
 
-	let path = path.join(filename);
+	write_source(&path.join(filename), content)
+}
+
+/// Write the stub of a python module, `filename` is the module name.
+pub fn write_stub_to_file(path: &Path, filename: &str, content: &str) -> Result<()> {
+	let filename = filename.to_string() + STUB_FILE_EXTENSION;
+
+	write_source(&path.join(filename), content)
+}
+
+fn write_source(path: &Path, content: &str) -> Result<()> {
 	let mut file = match File::create(&path) {
-		Err(e) => panic!("Error occurred while trying to create file {} {:?}", e, path),
+		Err(e) => return Err(Error::io(path, e)),
 		Ok(file) => file,
 	};
 
-	match file.write_all(content.as_bytes()) {
-		Err(e) => println!("Error occurred while trying to write to file {}", e),
-		Ok(_) => println!("Successfully written content to a file"),
+	file.write_all(content.as_bytes()).map_err(|e| Error::io(path, e))
+}
+
+/// Check if a python module exists, `filename` is the module name.
+pub fn module_exists(path: &Path, filename: &str) -> bool {
+	path.join(filename.to_string() + FILE_EXTENSION).is_file()
+}
+
+/// Read the source of an existing python module.
+/// Returns None if the module does not exist yet.
+pub fn read_module(path: &Path, filename: &str) -> Result<Option<String>> {
+	let filename = filename.to_string() + FILE_EXTENSION;
+
+	let path = path.join(filename);
+	if !path.is_file() {
+		return Ok(None);
 	}
+
+	let content = read_file(&path)?;
+	Ok(Some(content))
 }
 
-pub fn get_toml_result(root: Root) -> String {
-    let config = Config {
-        root: root
+/// The content of a config file, read from stdin when `conf_file` is `-`.
+fn read_config_source(conf_file: &str) -> Result<String> {
+    if conf_file != STDIO {
+        return read_file(conf_file);
+    }
+
+    let mut content = String::new();
+    match io::stdin().read_to_string(&mut content) {
+        Ok(_) => Ok(content),
+        Err(e) => Err(Error::io("<stdin>", e))
+    }
+}
+
+/// Read and deserialize a config file. (Toml/Yaml/Json).
+/// The config is read from stdin when `conf_file` is `-`.
+/// Configs of older versions are upgraded to the current version, and the
+/// included package files are added to their packages.
+pub fn read_config(conf_file: &str, conf_type: ConfType) -> Result<Config> {
+    let file_content = read_config_source(conf_file)?;
+    let mut config: Config = from_versioned_str(Path::new(conf_file), &file_content, conf_type, migrate::upgrade)?;
+
+    include::resolve(&mut config.root, conf_file)?;
+    Ok(config)
+}
+
+/// Read a package file included by a package, the type follows the extension.
+pub fn read_package_file(path: &Path) -> Result<PackageFile> {
+    let conf_type = match ConfType::from_path(path) {
+        Some(conf_type) => conf_type,
+        None => {
+            let message = "Unable to tell the config type of an included file from its extension".to_string();
+            return Err(Error::Config {path: path.into(), line: None, column: None, message: message});
+        }
     };
 
-    let toml_res = toml::Value::try_from(&config).unwrap();
-    toml_res.to_string()
+    let file_content = read_file(path)?;
+    from_versioned_str(path, &file_content, conf_type, migrate::upgrade_package_file)
+}
+
+/// Rewrite a config or a package file in the current version.
+/// Included files are not read, they are migrated on their own.
+pub fn migrate_config(conf_file: &str, conf_type: ConfType) -> Result<String> {
+    let file_content = read_config_source(conf_file)?;
+    let conf_file = Path::new(conf_file);
+    let value: serde_json::Value = from_config_str(conf_file, &file_content, conf_type)?;
+
+    // Package files have no root.
+    if value.get("root").is_some() {
+        let config: Config = from_versioned_str(conf_file, &file_content, conf_type, migrate::upgrade)?;
+        config_to_string(&config, conf_type)
+    } else {
+        let package_file: PackageFile = from_versioned_str(conf_file, &file_content, conf_type, migrate::upgrade_package_file)?;
+        config_to_string(&package_file, conf_type)
+    }
 }
 
-pub fn get_yaml_result(root: Root) -> String {
-    let config = Config {
-        root: root
+/// Deserialize a config or a package file, older versions are upgraded
+/// to the current version with `upgrade`.
+fn from_versioned_str<T>(conf_file: &Path, content: &str, conf_type: ConfType, upgrade: fn(serde_json::Value, u64) -> serde_json::Value) -> Result<T>
+    where T: DeserializeOwned
+{
+    let value: serde_json::Value = from_config_str(conf_file, content, conf_type)?;
+    let config_error = |message: String| Error::Config {path: conf_file.into(), line: None, column: None, message: message};
+
+    let version = migrate::version(&value).map_err(&config_error)?;
+    if version == CONFIG_VERSION {
+        // Deserialize the content again, the errors of a Value have no line.
+        return from_config_str(conf_file, content, conf_type);
+    }
+
+    serde_json::from_value(upgrade(value, version)).map_err(|e| config_error(e.to_string()))
+}
+
+fn from_config_str<T: DeserializeOwned>(conf_file: &Path, content: &str, conf_type: ConfType) -> Result<T> {
+    match conf_type {
+        ConfType::Toml => toml::from_str(content).map_err(|e| Error::toml(conf_file, e)),
+        ConfType::Yaml => serde_yaml::from_str(content).map_err(|e| Error::yaml(conf_file, e)),
+        ConfType::Json => serde_json::from_str(content).map_err(|e| Error::json(conf_file, e))
+    }
+}
+
+/// Read the project settings from .x-ray.toml in `dir`, the directory of the config.
+/// Returns the default settings if there is no settings file.
+pub fn read_settings(dir: &Path) -> Result<Settings> {
+    let settings_file = dir.join(SETTINGS_FILE);
+    if !settings_file.is_file() {
+        return Ok(Settings::default());
+    }
+
+    let content = read_file(&settings_file)?;
+    let settings: Settings = toml::from_str(&content).map_err(|e| Error::toml(settings_file.clone(), e))?;
+
+    if let Err(message) = settings.validation.check() {
+        return Err(Error::Config {path: settings_file, line: None, column: None, message: message});
+    }
+
+    Ok(settings)
+}
+
+/// Serialize a config or a package file. (Toml/Yaml/Json).
+/// Toml and json are sorted by key, yaml follows the order of the fields.
+pub fn config_to_string<T: Serialize>(config: &T, conf_type: ConfType) -> Result<String> {
+    let res = match conf_type {
+        ConfType::Toml => toml::Value::try_from(config).map(|x| x.to_string()).map_err(|e| e.to_string()),
+        ConfType::Yaml => serde_yaml::to_string(config).map_err(|e| e.to_string()),
+        // Objects of a json Value are sorted by key.
+        ConfType::Json => serde_json::to_value(config)
+            .and_then(|x| serde_json::to_string_pretty(&x))
+            .map(|x| x + "\n")
+            .map_err(|e| e.to_string())
     };
 
-    let yaml_res = serde_yaml::to_string(&config).unwrap();
-    yaml_res.to_string()
+    res.map_err(|message| Error::Serialize {message: message})
 }
 
-/// Write the parsed content to a config file. (Toml/Yaml).
-pub fn write_to_config(conf_file: &str, toml_res: String) {
-    let file = fs::File::create(conf_file);
-    let mut file = match file {
+pub fn get_toml_result(root: Root) -> Result<String> {
+    config_to_string(&Config::new(root), ConfType::Toml)
+}
+
+pub fn get_yaml_result(root: Root) -> Result<String> {
+    config_to_string(&Config::new(root), ConfType::Yaml)
+}
+
+/// Pretty printed json, with the keys sorted like in the toml output.
+pub fn get_json_result(root: Root) -> Result<String> {
+    config_to_string(&Config::new(root), ConfType::Json)
+}
+
+/// Write the parsed content to a config file. (Toml/Yaml/Json).
+/// The content is written to stdout when `conf_file` is `-`.
+pub fn write_to_config<P: AsRef<Path>>(conf_file: P, toml_res: String) -> Result<()> {
+    let conf_file = conf_file.as_ref();
+    if conf_file == Path::new(STDIO) {
+        return match io::stdout().write_all(toml_res.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(Error::io("<stdout>", e))
+        };
+    }
+
+    let mut file = match fs::File::create(conf_file) {
         Ok(f) => f,
-        Err(e) => panic!("Error {} {}", e, conf_file)
+        Err(e) => return Err(Error::io(conf_file, e))
     };
 
-    file.write_all(toml_res.as_bytes()).expect("Could not write config to file");
+    match file.write_all(toml_res.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::io(conf_file, e))
+    }
 }
 
-pub fn create_package(package_path: &Path) {
+pub fn create_package(package_path: &Path) -> Result<()> {
 	let init_file_path = INIT_FILE;
 
 	match fs::create_dir_all(package_path) {
         Ok(_) => {},
-        Err(e) => println!("Failed to create package {}", e)
+        Err(e) => return Err(Error::io(package_path, e))
     };
 
+	// Leave an existing __init__.py alone, it may have hand written code.
 	let path = package_path.join(init_file_path);
+	if path.exists() {
+		return Ok(());
+	}
+
 	match File::create(&path) {
-		Err(e) => panic!("Error occurred while trying to create file {}", e),
-		Ok(file) => file,
-	};
-}
\ No newline at end of file
+		Err(e) => Err(Error::io(path, e)),
+		Ok(_) => Ok(()),
+	}
+}
@@ -0,0 +1,273 @@
+use regex::Regex;
+
+use error::Violation;
+use structures::{Root, Package, Module, Class, Function, Variable, Validation, Validate, dotted_path};
+
+// PEP 8 naming conventions.
+const PACKAGE_RULE: &'static str = r"^[a-z][a-z0-9_]*$";
+const MODULE_RULE: &'static str = r"^_?[a-z][a-z0-9_]*$";
+const CLASS_RULE: &'static str = r"^_?[A-Z][a-zA-Z0-9]*$";
+const FUNCTION_RULE: &'static str = r"^_{0,2}[a-z][a-z0-9_]*$";
+const METHOD_RULE: &'static str = r"^_{0,2}[a-z][a-z0-9_]*$";
+const PARAMETER_RULE: &'static str = r"^_?[a-z][a-z0-9_]*$";
+const CONSTANT_RULE: &'static str = r"^_?[A-Z][A-Z0-9_]*$";
+
+/// The compiled naming rules.
+pub struct Rules {
+    packages: Regex,
+    modules: Regex,
+    classes: Regex,
+    functions: Regex,
+    methods: Regex,
+    parameters: Regex,
+    constants: Regex,
+}
+
+/// Compile a rule, falling back to the default when it is not set.
+/// Rules are checked with `Validation::check` when they are read.
+fn compile(rule: Option<String>, default: &str) -> Regex {
+    let rule = rule.unwrap_or(default.to_string());
+    Regex::new(&rule).unwrap()
+}
+
+impl Rules {
+    pub fn new(validation: Validation) -> Rules {
+        Rules {
+            packages: compile(validation.packages, PACKAGE_RULE),
+            modules: compile(validation.modules, MODULE_RULE),
+            classes: compile(validation.classes, CLASS_RULE),
+            functions: compile(validation.functions, FUNCTION_RULE),
+            methods: compile(validation.methods, METHOD_RULE),
+            parameters: compile(validation.parameters, PARAMETER_RULE),
+            constants: compile(validation.constants, CONSTANT_RULE)
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::new(Validation::default())
+    }
+}
+
+/// Check an item against a rule, recording a violation when it doesn't match.
+fn check<T: Validate>(item: &T, path: String, kind: &str, re: &Regex, violations: &mut Vec<Violation>) {
+    if !item.validate_case(re) {
+        let rule = format!("{} names must match {}", kind, re.as_str());
+        violations.push(Violation::new(path, &rule));
+    }
+}
+
+fn validate_function(function: &Function, path: &str, kind: &str, re: &Regex, rules: &Rules, violations: &mut Vec<Violation>) {
+    let function_path = dotted_path(path, &function.name);
+
+    check(function, function_path.clone(), kind, re, violations);
+
+    for parameter in &function.parameters {
+        check(parameter, dotted_path(&function_path, &parameter.name), "parameter", &rules.parameters, violations);
+    }
+}
+
+fn validate_class(class: &Class, path: &str, rules: &Rules, violations: &mut Vec<Violation>) {
+    let class_path = dotted_path(path, &class.name);
+
+    check(class, class_path.clone(), "class", &rules.classes, violations);
+
+    for method in &class.methods {
+        validate_function(method, &class_path, "method", &rules.methods, rules, violations);
+    }
+
+    for nested_class in &class.classes {
+        validate_class(nested_class, &class_path, rules, violations);
+    }
+}
+
+/// Whether a module level variable is a constant, i.e. it is annotated with
+/// `Final` or `Final[...]`, optionally as `typing.Final`.
+fn is_constant(variable: &Variable) -> bool {
+    match variable.annotation {
+        Some(ref annotation) => {
+            let annotation = annotation.trim();
+            let annotation = annotation.trim_left_matches("typing.");
+
+            annotation == "Final" || annotation.starts_with("Final[")
+        },
+        None => false
+    }
+}
+
+fn validate_module(module: &Module, path: &str, rules: &Rules, violations: &mut Vec<Violation>) {
+    let module_path = dotted_path(path, &module.name);
+
+    check(module, module_path.clone(), "module", &rules.modules, violations);
+
+    // Only the variables declared as constants are checked, ordinary ones
+    // like `app = Flask(__name__)` are left alone.
+    for variable in &module.variables {
+        if is_constant(variable) {
+            check(variable, dotted_path(&module_path, &variable.name), "constant", &rules.constants, violations);
+        }
+    }
+
+    for class in &module.classes {
+        validate_class(class, &module_path, rules, violations);
+    }
+
+    for function in &module.functions {
+        validate_function(function, &module_path, "function", &rules.functions, rules, violations);
+    }
+}
+
+fn validate_package(package: &Package, path: &str, rules: &Rules, violations: &mut Vec<Violation>) {
+    let package_path = dotted_path(path, &package.name);
+
+    check(package, package_path.clone(), "package", &rules.packages, violations);
+
+    for module in &package.modules {
+        validate_module(module, &package_path, rules, violations);
+    }
+
+    for nested_package in &package.packages {
+        validate_package(nested_package, &package_path, rules, violations);
+    }
+}
+
+/// Walk the whole config and collect every violation.
+/// Paths are dotted from the top level package or module,
+/// e.g. `sample.display.Animal.get_animal`.
+pub fn validate(root: &Root, rules: &Rules) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    for module in &root.modules {
+        validate_module(module, "", rules, &mut violations);
+    }
+
+    for package in &root.packages {
+        validate_package(package, "", rules, &mut violations);
+    }
+
+    violations
+}
+
+#[test]
+fn test_validate_collects_all_violations() {
+    use structures::Parameter;
+
+    let method = Function {
+        name: "getAnimal".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: vec![Parameter::new("self")]
+    };
+
+    let class = Class {
+        name: "animal".to_string(),
+        description: None,
+        parents: Vec::new(),
+        decorators: Vec::new(),
+        classes: Vec::new(),
+        attributes: Vec::new(),
+        methods: vec![method]
+    };
+
+    let module = Module {
+        name: "display".to_string(),
+        description: None,
+        imports: Vec::new(),
+        exports: Vec::new(),
+        variables: vec![
+            Variable::from_source("max_legs: Final = 4").unwrap(),
+            Variable::from_source("MAX_LEGS = 4").unwrap(),
+            Variable::from_source("logger = None").unwrap(),
+            Variable::from_source("__version__ = \"1.0\"").unwrap(),
+        ],
+        functions: Vec::new(),
+        classes: vec![class]
+    };
+
+    let function = Function {
+        name: "Main".to_string(),
+        description: None,
+        returns: None,
+        is_async: false,
+        decorators: Vec::new(),
+        parameters: Vec::new()
+    };
+
+    let root = Root {
+        name: "root".to_string(),
+        packages: vec![Package {
+            name: "sample".to_string(),
+            include: Vec::new(),
+            packages: vec![Package {
+                name: "nested".to_string(),
+                include: Vec::new(),
+                packages: Vec::new(),
+                modules: vec![module]
+            }],
+            modules: Vec::new()
+        }],
+        modules: vec![Module {
+            name: "main".to_string(),
+            description: None,
+            imports: Vec::new(),
+            exports: Vec::new(),
+            variables: Vec::new(),
+            functions: vec![function],
+            classes: Vec::new()
+        }]
+    };
+
+    let violations = validate(&root, &Rules::default());
+
+    assert_eq!(violations, vec![
+        Violation::new("main.Main".to_string(), &format!("function names must match {}", FUNCTION_RULE)),
+        Violation::new("sample.nested.display.max_legs".to_string(), &format!("constant names must match {}", CONSTANT_RULE)),
+        Violation::new("sample.nested.display.animal".to_string(), &format!("class names must match {}", CLASS_RULE)),
+        Violation::new("sample.nested.display.animal.getAnimal".to_string(), &format!("method names must match {}", METHOD_RULE)),
+    ]);
+}
+
+#[test]
+fn test_validate_default_rules() {
+    let rules = Rules::default();
+
+    for name in &["HttpClient", "Base64Codec", "_Private"] {
+        assert!(rules.classes.is_match(name), "{}", name);
+    }
+    for name in &["get_v2", "parse_utf8", "__init__", "_helper"] {
+        assert!(rules.functions.is_match(name), "{}", name);
+        assert!(rules.methods.is_match(name), "{}", name);
+    }
+    for name in &["MAX_RETRIES", "HTTP2"] {
+        assert!(rules.constants.is_match(name), "{}", name);
+    }
+
+    assert!(!rules.classes.is_match("http_client"));
+    assert!(!rules.functions.is_match("getValue"));
+    assert!(!rules.constants.is_match("max_retries"));
+}
+
+#[test]
+fn test_validate_constants() {
+    for source in &["MAX_RETRIES: Final = 3", "MAX_RETRIES: Final[int] = 3", "MAX_RETRIES: typing.Final[int] = 3"] {
+        assert!(is_constant(&Variable::from_source(source).unwrap()), "{}", source);
+    }
+    for source in &["MAX_RETRIES = 3", "retries: int = 3", "mode: Finality", "__version__ = \"1.0\""] {
+        assert!(!is_constant(&Variable::from_source(source).unwrap()), "{}", source);
+    }
+}
+
+#[test]
+fn test_validate_custom_rules() {
+    let validation = Validation {
+        classes: Some(r"^[A-Z][a-z]+$".to_string()),
+        ..Validation::default()
+    };
+    let rules = Rules::new(validation);
+
+    assert!(!rules.classes.is_match("HttpClient"));
+    assert!(rules.functions.is_match("get_v2"));
+}
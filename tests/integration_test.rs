@@ -2,9 +2,11 @@ extern crate x_ray;
 
 mod util;
 
+use std::fs;
 use std::path::Path;
 
-use x_ray::cli::ConfType;
+use x_ray::cli::{ConfType, Order};
+use x_ray::{Error, GenOptions};
 
 
 #[test]
@@ -17,20 +19,17 @@ fn test_src_parse_gen_toml() {
     // Test codegen.
     let test_gen_toml_content = util::read_file(conf_file_gen);
     let parse_dir = "tests/test_output/src/test_py_project";
-    x_ray::gen(util::get_current_directory() + "/" + parse_dir, conf_file_gen, ConfType::Toml);
+    x_ray::gen(util::get_current_directory() + "/" + parse_dir, conf_file_gen, ConfType::Toml).unwrap();
 
     // Test parse.
-    x_ray::util::write_to_config(&conf_file_parse, x_ray::parse(parse_dir, ConfType::Toml));
+    x_ray::util::write_to_config(&conf_file_parse, x_ray::parse(parse_dir, ConfType::Toml).unwrap()).unwrap();
     let test_parse_toml_content = util::read_file(conf_file_parse);
 
     assert_eq!(test_gen_toml_content, test_parse_toml_content);
 
-    // clean test output directory
-    let output_src_path = Path::new("tests/test_output/src");
-    let output_config_path = Path::new("tests/test_output/conf");
-
-    util::clean_dir(output_src_path);
-    util::clean_dir(output_config_path);
+    // clean test output
+    util::clean_dir(Path::new(parse_dir));
+    fs::remove_file(conf_file_parse).unwrap();
 }
 
 #[test]
@@ -42,21 +41,41 @@ fn test_src_parse_gen_yaml() {
 
     // Test codegen.
     let test_gen_yaml_content = util::read_file(conf_file_gen);
-    let parse_dir = "tests/test_output/src/test_py_project";
-    x_ray::gen(util::get_current_directory() + "/" + parse_dir, conf_file_gen, ConfType::Yaml);
+    let parse_dir = "tests/test_output/src/test_py_project_yaml";
+    x_ray::gen(util::get_current_directory() + "/" + parse_dir, conf_file_gen, ConfType::Yaml).unwrap();
 
     // Test parse.
-    x_ray::util::write_to_config(&conf_file_parse, x_ray::parse(parse_dir, ConfType::Yaml));
+    x_ray::util::write_to_config(&conf_file_parse, x_ray::parse(parse_dir, ConfType::Yaml).unwrap()).unwrap();
     let test_parse_yaml_content = util::read_file(conf_file_parse);
 
     assert_eq!(test_gen_yaml_content, test_parse_yaml_content);
 
-    // clean test output directory
-    let output_src_path = Path::new("tests/test_output/src");
-    let output_config_path = Path::new("tests/test_output/conf");
+    // clean test output
+    util::clean_dir(Path::new(parse_dir));
+    fs::remove_file(conf_file_parse).unwrap();
+}
+
+#[test]
+fn test_src_parse_gen_json() {
+    util::create_test_dirs().unwrap();
 
-    util::clean_dir(output_src_path);
-    util::clean_dir(output_config_path);
+    let conf_file_gen = "tests/test_input/conf/test_gen.json";
+    let conf_file_parse = "tests/test_output/conf/test_parse.json";
+
+    // Test codegen.
+    let test_gen_json_content = util::read_file(conf_file_gen);
+    let parse_dir = "tests/test_output/src/test_py_project_json";
+    x_ray::gen(util::get_current_directory() + "/" + parse_dir, conf_file_gen, ConfType::Json).unwrap();
+
+    // Test parse.
+    x_ray::util::write_to_config(&conf_file_parse, x_ray::parse(parse_dir, ConfType::Json).unwrap()).unwrap();
+    let test_parse_json_content = util::read_file(conf_file_parse);
+
+    assert_eq!(test_gen_json_content, test_parse_json_content);
+
+    // clean test output
+    util::clean_dir(Path::new(parse_dir));
+    fs::remove_file(conf_file_parse).unwrap();
 }
 
 #[test]
@@ -66,9 +85,11 @@ fn test_src_main() {
 
     let src_input = "tests/test_input/src";
 
-    let actual_output_str = x_ray::parse(src_input, ConfType::Toml);
+    let actual_output_str = x_ray::parse(src_input, ConfType::Toml).unwrap();
     let exepected_output_str = 
-r#"[root]
+r#"version = 2
+
+[root]
 name = "tests/test_input/src"
 packages = []
 
@@ -81,12 +102,630 @@ name = "main"
 description = "Main function"
 name = "main"
 parameters = []
+
+[[root.modules.imports]]
+name = "os"
 "#;
     assert_eq!(actual_output_str, exepected_output_str);
+}
+
+#[test]
+fn test_src_gen_merge() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file_gen = "tests/test_input/conf/test_gen.toml";
+    let conf_file_merge = "tests/test_output/conf/test_merge.toml";
+    let gen_dir = "tests/test_output/src/test_merge_project";
+
+    x_ray::gen(util::get_current_directory() + "/" + gen_dir, conf_file_gen, ConfType::Toml).unwrap();
+
+    // Fill in a function body and add some hand written code.
+    let api_file = gen_dir.to_string() + "/api/api.py";
+    let api_content = util::read_file(&api_file).replace("pass", "return 42") + r#"
+def helper():
+    return 0
+"#;
+    util::write_file(&api_file, &api_content);
+
+    // Add a new function to the display module.
+    let merge_conf_content = util::read_file(conf_file_gen) + r#"
+[[root.packages.modules.functions]]
+description = "this is the hide function"
+name = "hide"
+parameters = ["msg"]
+"#;
+    util::write_file(conf_file_merge, &merge_conf_content);
+
+    let options = GenOptions {merge: true, ..GenOptions::default()};
+    x_ray::gen_with_options(util::get_current_directory() + "/" + gen_dir, conf_file_merge, ConfType::Toml, &options).unwrap();
+
+    assert_eq!(util::read_file(&api_file), api_content);
+
+    let display_content = util::read_file(&(gen_dir.to_string() + "/sample/display.py"));
+    assert_eq!(display_content.matches("def display(msg):").count(), 1);
+    assert_eq!(display_content.matches("def get_animal(self):").count(), 1);
+    assert!(display_content.ends_with(r#"
+def hide(msg):
+    """
+    this is the hide function
+    """
+    pass
+"#));
+
+    util::clean_dir(Path::new(gen_dir));
+    fs::remove_file(conf_file_merge).unwrap();
+}
+
+#[test]
+fn test_src_gen_merge_unparsable_file() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_input/conf/test_gen.toml";
+    let gen_dir = "tests/test_output/src/test_merge_unparsable_project";
+
+    x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+
+    // The functions after the broken one would be added a second time.
+    let display_file = gen_dir.to_string() + "/sample/display.py";
+    let display_content = "def broken(:\n    pass\n\n".to_string() + &util::read_file(&display_file);
+    util::write_file(&display_file, &display_content);
+
+    let options = GenOptions {merge: true, ..GenOptions::default()};
+    match x_ray::gen_with_options(gen_dir.to_string(), conf_file, ConfType::Toml, &options) {
+        Err(Error::Parse {file, offset}) => {
+            assert_eq!(file, Path::new(&display_file));
+            assert_eq!(offset, 0);
+        },
+        result => panic!("Expected a parse error, got {:?}", result)
+    }
+    assert_eq!(util::read_file(&display_file), display_content);
+
+    util::clean_dir(Path::new(gen_dir));
+}
+
+
+#[test]
+fn test_gen_missing_conf_file() {
+    let result = x_ray::gen("tests/test_output/src/test_missing".to_string(), "tests/test_input/conf/missing.toml", ConfType::Toml);
+
+    match result {
+        Err(Error::Io {path, ..}) => assert_eq!(path, Path::new("tests/test_input/conf/missing.toml")),
+        _ => panic!("Expected an io error, got {:?}", result)
+    }
+}
+
+#[test]
+fn test_gen_invalid_conf_file() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_output/conf/test_invalid.toml";
+    util::write_file(conf_file, "[root]\nname = root\n");
+
+    let result = x_ray::gen("tests/test_output/src/test_invalid".to_string(), conf_file, ConfType::Toml);
+
+    match result {
+        Err(Error::Config {line, ..}) => assert_eq!(line, Some(2)),
+        _ => panic!("Expected a config error, got {:?}", result)
+    }
+}
+
+#[test]
+fn test_gen_invalid_json_conf_file() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_output/conf/test_invalid.json";
+    util::write_file(conf_file, "{\n  \"root\": {\n    \"name\": root\n  }\n}\n");
+
+    let result = x_ray::gen("tests/test_output/src/test_invalid_json".to_string(), conf_file, ConfType::Json);
+
+    match result {
+        Err(Error::Config {line, ..}) => assert_eq!(line, Some(3)),
+        _ => panic!("Expected a config error, got {:?}", result)
+    }
+}
+
+#[test]
+fn test_gen_validation_rules_from_config() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_output/conf/test_validation.toml";
+    let gen_dir = "tests/test_output/src/test_validation_project";
+    let conf_content = r#"[root]
+name = "root"
+
+[[root.modules]]
+name = "main"
+
+[[root.modules.functions]]
+name = "getValue"
+"#;
+    util::write_file(conf_file, conf_content);
+
+    match x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml) {
+        Err(Error::Validation {violations}) => assert_eq!(violations[0].path, "main.getValue"),
+        result => panic!("Expected a validation error, got {:?}", result)
+    }
+
+    util::write_file(conf_file, &(conf_content.to_string() + r#"
+[validation]
+functions = "^[a-z][a-zA-Z]*$"
+"#));
+
+    x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+
+    util::clean_dir(Path::new(gen_dir));
+}
+
+#[test]
+fn test_gen_validation_rules_from_settings_file() {
+    util::create_test_dirs().unwrap();
+
+    let conf_dir = "tests/test_output/conf/settings";
+    let conf_file = "tests/test_output/conf/settings/project.toml";
+    let gen_dir = "tests/test_output/src/test_settings_project";
+    fs::create_dir_all(conf_dir).unwrap();
+    util::write_file(conf_file, r#"[root]
+name = "root"
+
+[[root.modules]]
+name = "main"
+
+[[root.modules.functions]]
+name = "getValue"
+"#);
+
+    // The settings file is read from the directory of the config.
+    util::write_file(&(conf_dir.to_string() + "/.x-ray.toml"), r#"[validation]
+functions = "^[a-z][a-zA-Z]*$"
+"#);
+    x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+
+    fs::remove_file(conf_dir.to_string() + "/.x-ray.toml").unwrap();
+    match x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml) {
+        Err(Error::Validation {violations}) => assert_eq!(violations[0].path, "main.getValue"),
+        result => panic!("Expected a validation error, got {:?}", result)
+    }
+
+    util::clean_dir(Path::new(conf_dir));
+    util::clean_dir(Path::new(gen_dir));
+}
+
+#[test]
+fn test_src_check() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_input/conf/test_gen.toml";
+    let gen_dir = "tests/test_output/src/test_check_project";
+
+    x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+    assert_eq!(x_ray::check(gen_dir, conf_file, ConfType::Toml).unwrap(), Vec::new());
+
+    let api_file = gen_dir.to_string() + "/api/api.py";
+    let api_content = util::read_file(&api_file) + r#"
+def helper():
+    pass
+"#;
+    util::write_file(&api_file, &api_content);
+
+    let differences = x_ray::check(gen_dir, conf_file, ConfType::Toml).unwrap();
+    let differences = differences.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    assert_eq!(differences, vec!["extra function api.api.helper".to_string()]);
+
+    util::clean_dir(Path::new(gen_dir));
+}
+
+#[test]
+fn test_src_parse_order() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_output/conf/test_order.toml";
+    let parse_dir = "tests/test_output/src/test_order_project";
+    util::write_file(conf_file, r#"[root]
+name = "root"
+
+[[root.modules]]
+name = "main"
+
+[[root.modules.functions]]
+name = "zeta"
+
+[[root.modules.functions]]
+name = "alpha"
+"#);
+
+    x_ray::gen(parse_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+
+    let by_name = x_ray::parse(parse_dir, ConfType::Toml).unwrap();
+    assert!(by_name.find("alpha").unwrap() < by_name.find("zeta").unwrap());
+
+    let by_declaration = x_ray::parse_with_order(parse_dir, ConfType::Toml, Order::Declaration).unwrap();
+    assert!(by_declaration.find("zeta").unwrap() < by_declaration.find("alpha").unwrap());
+
+    // Parsing is stable across runs.
+    assert_eq!(x_ray::parse(parse_dir, ConfType::Toml).unwrap(), by_name);
+
+    util::clean_dir(Path::new(parse_dir));
+    fs::remove_file(conf_file).unwrap();
+}
+
+#[test]
+fn test_src_parse_gen_module_variables() {
+    util::create_test_dirs().unwrap();
 
-    let output_src_path = Path::new("tests/test_output/src");
-    let output_config_path = Path::new("tests/test_output/conf");
+    let parse_dir = "tests/test_output/src/test_variables_project";
+    let gen_dir = "tests/test_output/src/test_variables_project_gen";
+    let conf_file = "tests/test_output/conf/test_variables.toml";
+    fs::create_dir_all(parse_dir).unwrap();
+    util::write_file(&(parse_dir.to_string() + "/app.py"), r#"from flask import Flask
 
-    util::clean_dir(output_src_path);
-    util::clean_dir(output_config_path);
-}
\ No newline at end of file
+MAX_RETRIES = 3
+app = Flask(__name__)
+logger = None
+"#);
+
+    // Ordinary variables are not constants, the round trip passes validation.
+    x_ray::util::write_to_config(conf_file, x_ray::parse(parse_dir, ConfType::Toml).unwrap()).unwrap();
+    x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+    let gen_content = util::read_file(&(gen_dir.to_string() + "/app.py"));
+    assert_eq!(gen_content.trim(), util::read_file(&(parse_dir.to_string() + "/app.py")).trim());
+
+    util::clean_dir(Path::new(parse_dir));
+    util::clean_dir(Path::new(gen_dir));
+    fs::remove_file(conf_file).unwrap();
+}
+
+#[test]
+fn test_src_parse_gen_empty_nested_class() {
+    util::create_test_dirs().unwrap();
+
+    let parse_dir = "tests/test_output/src/test_empty_class_project";
+    let gen_dir = "tests/test_output/src/test_empty_class_project_gen";
+    let conf_file = "tests/test_output/conf/test_empty_class.toml";
+    fs::create_dir_all(parse_dir).unwrap();
+    util::write_file(&(parse_dir.to_string() + "/app.py"), r#"class Outer:
+    class Inner: pass
+"#);
+
+    // The empty body of the nested class is generated as `pass`.
+    x_ray::util::write_to_config(conf_file, x_ray::parse(parse_dir, ConfType::Toml).unwrap()).unwrap();
+    x_ray::gen(gen_dir.to_string(), conf_file, ConfType::Toml).unwrap();
+    let gen_content = util::read_file(&(gen_dir.to_string() + "/app.py"));
+    assert_eq!(gen_content.trim(), "class Outer:\n    \n\n    class Inner:\n        pass");
+
+    util::clean_dir(Path::new(parse_dir));
+    util::clean_dir(Path::new(gen_dir));
+    fs::remove_file(conf_file).unwrap();
+}
+
+#[test]
+fn test_src_parse_inconsistent_indentation() {
+    util::create_test_dirs().unwrap();
+
+    let parse_dir = "tests/test_output/src/test_indentation_project";
+    fs::create_dir_all(parse_dir).unwrap();
+    util::write_file(&(parse_dir.to_string() + "/main.py"), "def main():\n\tif True:\n        pass\n");
+
+    match x_ray::parse(parse_dir, ConfType::Toml) {
+        Err(Error::Indentation {line, ..}) => assert_eq!(line, 3),
+        result => panic!("Expected an indentation error, got {:?}", result)
+    }
+
+    util::clean_dir(Path::new(parse_dir));
+}
+
+#[test]
+fn test_src_parse_error_offset() {
+    util::create_test_dirs().unwrap();
+
+    let parse_dir = "tests/test_output/src/test_parse_error_project";
+    fs::create_dir_all(parse_dir).unwrap();
+    let main_content = "\n\n# The main module.\ndef main():\n    pass\n\ndef broken(:\n    pass\n";
+    util::write_file(&(parse_dir.to_string() + "/main.py"), main_content);
+
+    // The offset is in the file, the leading empty lines included.
+    match x_ray::parse(parse_dir, ConfType::Toml) {
+        Err(Error::Parse {offset, ..}) => assert_eq!(offset, main_content.find("def broken").unwrap()),
+        result => panic!("Expected a parse error, got {:?}", result)
+    }
+
+    util::clean_dir(Path::new(parse_dir));
+}
+
+#[test]
+fn test_src_gen_stubs() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_input/conf/test_gen.toml";
+    let gen_dir = "tests/test_output/src/test_stubs_project";
+
+    let options = GenOptions {stubs: true, ..GenOptions::default()};
+    x_ray::gen_with_options(gen_dir.to_string(), conf_file, ConfType::Toml, &options).unwrap();
+
+    assert_eq!(util::read_file(&(gen_dir.to_string() + "/api/api.pyi")), r#"import json
+from typing import Dict
+
+__all__ = ["create_api"]
+
+MAX_APIS: int
+
+def create_api(id: int, new=...) -> dict: ...
+"#);
+
+    assert_eq!(util::read_file(&(gen_dir.to_string() + "/sample/display.pyi")), r#"class Animal(Object):
+    legs: int
+    @property
+    def get_animal(self): ...
+
+    class Meta:
+        abstract = ...
+
+def display(msg): ...
+"#);
+
+    // The python modules are generated as usual.
+    assert!(Path::new(&(gen_dir.to_string() + "/sample/display.py")).exists());
+
+    util::clean_dir(Path::new(gen_dir));
+}
+
+#[test]
+fn test_src_parse_stubs() {
+    util::create_test_dirs().unwrap();
+
+    let parse_dir = "tests/test_output/src/test_parse_stubs_project";
+    fs::create_dir_all(parse_dir).unwrap();
+    util::write_file(&(parse_dir.to_string() + "/api.py"), r#"
+MAX_APIS = 10
+
+def create_api(id, new=False):
+    """Create the api."""
+    return {}
+
+def load(data):
+    return data
+"#);
+    util::write_file(&(parse_dir.to_string() + "/api.pyi"), r#"from typing import overload
+
+MAX_APIS: int
+
+def create_api(id: int, new: bool = ...) -> dict: ...
+@overload
+def load(data: str) -> str: ...
+@overload
+def load(data: bytes) -> bytes: ...
+"#);
+    util::write_file(&(parse_dir.to_string() + "/types.pyi"), "class Api: ...\n");
+
+    let actual_output_str = x_ray::parse(parse_dir, ConfType::Toml).unwrap();
+    let expected_output_str = r#"version = 2
+
+[root]
+name = "tests/test_output/src/test_parse_stubs_project"
+packages = []
+
+[[root.modules]]
+classes = []
+name = "api"
+
+[[root.modules.functions]]
+description = "Create the api."
+name = "create_api"
+returns = "dict"
+
+[[root.modules.functions.parameters]]
+annotation = "int"
+name = "id"
+
+[[root.modules.functions.parameters]]
+annotation = "bool"
+default = "False"
+name = "new"
+
+[[root.modules.functions]]
+decorators = ["overload"]
+name = "load"
+returns = "str"
+
+[[root.modules.functions.parameters]]
+annotation = "str"
+name = "data"
+
+[[root.modules.functions]]
+decorators = ["overload"]
+name = "load"
+returns = "bytes"
+
+[[root.modules.functions.parameters]]
+annotation = "bytes"
+name = "data"
+
+[[root.modules.functions]]
+name = "load"
+
+[[root.modules.functions.parameters]]
+name = "data"
+
+[[root.modules.imports]]
+module = "typing"
+name = "overload"
+
+[[root.modules.variables]]
+annotation = "int"
+name = "MAX_APIS"
+value = "10"
+
+[[root.modules]]
+functions = []
+name = "types"
+
+[[root.modules.classes]]
+methods = []
+name = "Api"
+parents = []
+"#;
+    assert_eq!(actual_output_str, expected_output_str);
+
+    util::clean_dir(Path::new(parse_dir));
+}
+
+#[test]
+fn test_src_gen_tests() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_input/conf/test_gen.toml";
+    let gen_dir = "tests/test_output/src/test_tests_project";
+
+    let options = GenOptions {tests: true, ..GenOptions::default()};
+    x_ray::gen_with_options(gen_dir.to_string(), conf_file, ConfType::Toml, &options).unwrap();
+
+    let test_file = gen_dir.to_string() + "/tests/sample/test_display.py";
+    assert_eq!(util::read_file(&test_file), r#"
+"""
+Tests for the sample.display module.
+"""
+
+from sample.display import Animal, display
+
+class TestAnimal:
+    """
+    Tests for Animal.
+    """
+
+    def test_get_animal(self):
+        pass
+
+class TestAnimalMeta:
+    """
+    Tests for AnimalMeta.
+    """
+
+def test_display():
+    pass
+"#);
+    assert!(Path::new(&(gen_dir.to_string() + "/tests/__init__.py")).exists());
+    assert!(Path::new(&(gen_dir.to_string() + "/tests/sample/__init__.py")).exists());
+    assert!(Path::new(&(gen_dir.to_string() + "/tests/api/test_api.py")).exists());
+
+    // Tests that were filled in are kept.
+    let test_content = util::read_file(&test_file).replace("pass", "assert True");
+    util::write_file(&test_file, &test_content);
+    x_ray::gen_with_options(gen_dir.to_string(), conf_file, ConfType::Toml, &options).unwrap();
+    assert_eq!(util::read_file(&test_file), test_content);
+
+    util::clean_dir(Path::new(gen_dir));
+}
+
+#[test]
+fn test_migrate_v1_config() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_output/conf/test_migrate_v1.yaml";
+    util::write_file(conf_file, r#"---
+root:
+  name: root
+  modules:
+    - name: main
+      description: ~
+      functions:
+        - name: main
+          description: ~
+          parameters:
+            - argv
+            - "debug=False"
+"#);
+
+    let expected_output_str = r#"---
+version: 2
+root:
+  name: root
+  packages: []
+  modules:
+    - name: main
+      description: ~
+      functions:
+        - name: main
+          description: ~
+          parameters:
+            - name: argv
+            - name: debug
+              default: "False"
+      classes: []"#;
+    let migrated = x_ray::migrate(conf_file, ConfType::Yaml).unwrap();
+    assert_eq!(migrated, expected_output_str);
+
+    // A migrated config stays the same.
+    util::write_file(conf_file, &migrated);
+    assert_eq!(x_ray::migrate(conf_file, ConfType::Yaml).unwrap(), migrated);
+
+    fs::remove_file(conf_file).unwrap();
+}
+
+#[test]
+fn test_gen_newer_config_version() {
+    util::create_test_dirs().unwrap();
+
+    let conf_file = "tests/test_output/conf/test_newer_version.toml";
+    util::write_file(conf_file, "version = 3\n\n[root]\nname = \"root\"\n");
+
+    let result = x_ray::gen("tests/test_output/src/test_newer_version".to_string(), conf_file, ConfType::Toml);
+
+    match result {
+        Err(Error::Config {message, ..}) => assert!(message.contains("newer"), message),
+        _ => panic!("Expected a config error, got {:?}", result)
+    }
+}
+
+#[test]
+fn test_gen_included_package_files() {
+    util::create_test_dirs().unwrap();
+
+    let conf_dir = "tests/test_output/conf/test_include";
+    let gen_dir = "tests/test_output/src/test_include_project";
+    fs::create_dir_all(conf_dir.to_string() + "/api").unwrap();
+
+    let conf_file = conf_dir.to_string() + "/project.toml";
+    util::write_file(&conf_file, r#"version = 2
+
+[root]
+name = "root"
+
+[[root.packages]]
+include = ["api/*.yaml"]
+name = "api"
+
+[[root.packages.modules]]
+name = "api"
+"#);
+    util::write_file(&(conf_dir.to_string() + "/api/models.yaml"), r#"---
+version: 2
+modules:
+  - name: models
+    functions:
+      - name: load
+packages:
+  - name: v1
+    include:
+      - v1.json
+"#);
+    util::write_file(&(conf_dir.to_string() + "/api/v1.json"), r#"{"modules": [{"name": "routes"}]}"#);
+
+    x_ray::gen(gen_dir.to_string(), &conf_file, ConfType::Toml).unwrap();
+    assert!(Path::new(&(gen_dir.to_string() + "/api/api.py")).is_file());
+    assert!(Path::new(&(gen_dir.to_string() + "/api/models.py")).is_file());
+    assert!(Path::new(&(gen_dir.to_string() + "/api/v1/routes.py")).is_file());
+
+    // Split the parsed source again, the split config assembles the same root.
+    let split_file = conf_dir.to_string() + "/split.json";
+    x_ray::parse_split(gen_dir, &split_file, ConfType::Json).unwrap();
+    assert!(util::read_file(&split_file).contains("\"api.json\""));
+    assert_eq!(x_ray::check(gen_dir, &split_file, ConfType::Json).unwrap(), Vec::new());
+
+    util::write_file(&(conf_dir.to_string() + "/api/v1.json"), r#"{"packages": [{"name": "v2", "include": ["models.yaml"]}]}"#);
+    match x_ray::gen(gen_dir.to_string(), &conf_file, ConfType::Toml) {
+        Err(Error::Config {message, ..}) => assert!(message.contains("includes itself"), message),
+        result => panic!("Expected a config error, got {:?}", result)
+    }
+
+    util::clean_dir(Path::new(gen_dir));
+    util::clean_dir(Path::new(conf_dir));
+}
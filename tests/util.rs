@@ -22,6 +22,11 @@ pub fn read_file(filename: &str) -> String {
     file_content
 }
 
+pub fn write_file(filename: &str, content: &str) {
+    let mut file = File::create(filename).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+}
+
 pub fn get_current_directory() -> String {
     let cwd = env::current_dir().unwrap();
     cwd.to_str().unwrap().to_string()